@@ -0,0 +1,525 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::header::{HeaderName, CONTENT_TYPE, TE, USER_AGENT};
+use http::{HeaderMap, HeaderValue, Method, Version};
+use prost_reflect::prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, MethodDescriptor};
+use serde::Deserialize;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+use url::Url;
+
+use crate::{HttpResponse, TransportError};
+
+const GRPC_CONTENT_TYPE: &str = "application/grpc";
+const GRPC_STATUS_KEY: &str = "grpc-status";
+const GRPC_MESSAGE_KEY: &str = "grpc-message";
+const GRPC_TIMEOUT_KEY: &str = "grpc-timeout";
+
+/// Headers that are specific to a single HTTP/1.x connection and are not allowed in HTTP/2
+const CONNECTION_HEADERS: [&str; 5] = [
+    "host",
+    "connection",
+    "content-length",
+    "keep-alive",
+    "transfer-encoding",
+];
+
+/// A unary gRPC call, where the request message is given as YAML or JSON and is encoded to
+/// protobuf with the help of either `.proto` files or a compiled descriptor set.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GrpcRequest {
+    url: String,
+    #[serde(default)]
+    #[serde(with = "http_serde::header_map")]
+    headers: HeaderMap,
+    grpc: GrpcCall,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GrpcCall {
+    #[serde(default)]
+    proto: Vec<PathBuf>,
+    #[serde(default)]
+    include: Vec<PathBuf>,
+    descriptor_set: Option<PathBuf>,
+    service: String,
+    method: String,
+    #[serde(default)]
+    message: serde_yaml::Value,
+}
+
+impl GrpcRequest {
+    pub fn url(&self) -> Result<Url, url::ParseError> {
+        if self.url.starts_with("http://") || self.url.starts_with("https://") {
+            Url::parse(&self.url)
+        } else {
+            Url::parse(&format!("https://{}", &self.url))
+        }
+    }
+
+    /// The HTTP/2 path of the call, which is `/<package>.<service>/<method>`
+    pub fn path(&self) -> String {
+        format!("/{}/{}", self.grpc.service, self.grpc.method)
+    }
+
+    pub fn headers(&self) -> HeaderMap {
+        self.headers.clone()
+    }
+
+    /// Resolve any relative paths to `.proto` files, include directories and descriptor sets
+    /// against `dir`, which should be the directory of the request file.
+    pub fn resolve_paths(&mut self, dir: &Path) {
+        let resolve = |path: &PathBuf| -> PathBuf {
+            if path.is_relative() {
+                dir.join(path)
+            } else {
+                path.clone()
+            }
+        };
+
+        self.grpc.proto = self.grpc.proto.iter().map(resolve).collect();
+        self.grpc.include = self.grpc.include.iter().map(resolve).collect();
+        self.grpc.descriptor_set = self.grpc.descriptor_set.as_ref().map(resolve);
+    }
+
+    /// The request message as JSON, regardless of it being given as YAML or JSON in the request
+    /// file
+    pub fn message(&self) -> Result<serde_json::Value, GrpcError> {
+        match &self.grpc.message {
+            serde_yaml::Value::Null => Ok(serde_json::Value::Object(Default::default())),
+            serde_yaml::Value::String(json) => serde_json::from_str(json)
+                .map_err(|e| GrpcError::Message(format!("Invalid JSON in message: {e}"))),
+            yaml => serde_json::to_value(yaml)
+                .map_err(|e| GrpcError::Message(format!("Invalid YAML in message: {e}"))),
+        }
+    }
+
+    pub fn method_descriptor(&self) -> Result<MethodDescriptor, GrpcError> {
+        let pool: DescriptorPool = self.descriptor_pool()?;
+        let service = pool
+            .get_service_by_name(&self.grpc.service)
+            .ok_or_else(|| GrpcError::UnknownMethod(self.path()))?;
+
+        let method = service.methods().find(|method| method.name() == self.grpc.method);
+
+        match method {
+            Some(method) if method.is_client_streaming() || method.is_server_streaming() => {
+                Err(GrpcError::Streaming(self.path()))
+            }
+            Some(method) => Ok(method),
+            None => Err(GrpcError::UnknownMethod(self.path())),
+        }
+    }
+
+    fn descriptor_pool(&self) -> Result<DescriptorPool, GrpcError> {
+        if let Some(path) = &self.grpc.descriptor_set {
+            let bytes: Vec<u8> = std::fs::read(path)
+                .map_err(|e| GrpcError::Descriptor(format!("{}: {e}", path.display())))?;
+            return DescriptorPool::decode(bytes.as_slice())
+                .map_err(|e| GrpcError::Descriptor(format!("{}: {e}", path.display())));
+        }
+
+        if self.grpc.proto.is_empty() {
+            return Err(GrpcError::Descriptor(String::from(
+                "Either `proto` or `descriptor_set` must be given",
+            )));
+        }
+
+        // Default to the directories of the .proto files when no include paths are given
+        let includes: Vec<PathBuf> = if self.grpc.include.is_empty() {
+            self.grpc
+                .proto
+                .iter()
+                .filter_map(|path| path.parent())
+                .map(Path::to_path_buf)
+                .collect()
+        } else {
+            self.grpc.include.clone()
+        };
+
+        let mut compiler =
+            protox::Compiler::new(includes).map_err(|e| GrpcError::Descriptor(e.to_string()))?;
+
+        compiler
+            .open_files(&self.grpc.proto)
+            .map_err(|e| GrpcError::Descriptor(e.to_string()))?;
+
+        Ok(compiler.descriptor_pool())
+    }
+
+    fn encode(&self, method: &MethodDescriptor) -> Result<Bytes, GrpcError> {
+        let message: DynamicMessage = DynamicMessage::deserialize(method.input(), self.message()?)
+            .map_err(|e| GrpcError::Message(e.to_string()))?;
+
+        Ok(frame(&message.encode_to_vec()))
+    }
+}
+
+impl FromStr for GrpcRequest {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+/// Wrap an encoded message in the gRPC length-prefixed message framing, which is a one byte
+/// compression flag followed by the length of the message as a four byte big-endian integer.
+fn frame(message: &[u8]) -> Bytes {
+    let mut buf = BytesMut::with_capacity(message.len() + 5);
+    buf.put_u8(0);
+    buf.put_u32(message.len() as u32);
+    buf.put_slice(message);
+    buf.freeze()
+}
+
+fn unframe(mut buf: Bytes) -> Result<Bytes, GrpcError> {
+    if buf.len() < 5 {
+        return Err(GrpcError::Message(String::from("Incomplete message in response")));
+    }
+
+    let compressed: bool = buf.get_u8() == 1;
+    let len: usize = buf.get_u32() as usize;
+
+    if compressed {
+        Err(GrpcError::Message(String::from("Compressed responses are not supported")))
+    } else if buf.len() < len {
+        Err(GrpcError::Message(String::from("Incomplete message in response")))
+    } else {
+        Ok(buf.split_to(len))
+    }
+}
+
+pub fn call(request: GrpcRequest, timeout: Duration) -> Result<HttpResponse, GrpcError> {
+    Call::new(request)?.send(timeout)
+}
+
+/// A unary call that is prepared once and can then be sent several times, such as in a load test,
+/// without loading the descriptors, encoding the message or starting a runtime again
+pub struct Call {
+    request: GrpcRequest,
+    url: Url,
+    method: MethodDescriptor,
+    payload: Bytes,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl Call {
+    pub fn new(request: GrpcRequest) -> Result<Call, GrpcError> {
+        let url: Url = request.url().map_err(|e| GrpcError::Other(e.to_string()))?;
+        let method: MethodDescriptor = request.method_descriptor()?;
+        let payload: Bytes = request.encode(&method)?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| GrpcError::Other(e.to_string()))?;
+
+        Ok(Call {
+            request,
+            url,
+            method,
+            payload,
+            runtime,
+        })
+    }
+
+    pub fn send(&self, timeout: Duration) -> Result<HttpResponse, GrpcError> {
+        let (url, method) = (&self.url, &self.method);
+        let exchange = exchange(url, &self.request, timeout, self.payload.clone());
+        let (parts, body, trailers) = self
+            .runtime
+            .block_on(async { tokio::time::timeout(timeout, exchange).await })
+            .map_err(|_| TransportError::Timeout(url.clone(), timeout))??;
+
+        let mut headers: HeaderMap = parts.headers;
+        headers.extend(trailers);
+
+        // A response without a message, a "trailers-only" response, carries the status in the
+        // headers
+        let code: u16 = headers
+            .get(GRPC_STATUS_KEY)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(2);
+
+        let body: String = if code == 0 {
+            let message = DynamicMessage::decode(method.output(), unframe(body)?)
+                .map_err(|e| GrpcError::Message(e.to_string()))?;
+            serde_json::to_string(&message).map_err(|e| GrpcError::Message(e.to_string()))?
+        } else {
+            let message: &str = headers
+                .get(GRPC_MESSAGE_KEY)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            serde_json::json!({ "code": code, "message": message }).to_string()
+        };
+
+        Ok(HttpResponse::new(String::from("HTTP/2.0"), http_status(code), headers, body))
+    }
+}
+
+type Exchange = (http::response::Parts, Bytes, HeaderMap);
+
+async fn exchange(
+    url: &Url,
+    request: &GrpcRequest,
+    timeout: Duration,
+    payload: Bytes,
+) -> Result<Exchange, GrpcError> {
+    let host: &str = url.host_str().ok_or_else(|| TransportError::UnknownHost(url.clone()))?;
+    let port: u16 = url.port_or_known_default().unwrap_or(443);
+
+    let tcp: TcpStream = TcpStream::connect((host, port))
+        .await
+        .map_err(|_| TransportError::Connection(url.clone()))?;
+
+    let request: http::Request<()> = http_request(url, request, timeout)?;
+
+    if url.scheme() == "https" {
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let mut config =
+            ClientConfig::builder().with_root_certificates(roots).with_no_client_auth();
+        config.alpn_protocols = vec![b"h2".to_vec()];
+
+        let server_name = ServerName::try_from(host.to_string())
+            .map_err(|_| TransportError::UnknownHost(url.clone()))?;
+
+        let tls = TlsConnector::from(Arc::new(config))
+            .connect(server_name, tcp)
+            .await
+            .map_err(|e| TransportError::Other(format!("TLS handshake failed: {e}")))?;
+
+        send(tls, request, payload, url).await
+    } else {
+        send(tcp, request, payload, url).await
+    }
+}
+
+async fn send<T>(
+    io: T,
+    request: http::Request<()>,
+    payload: Bytes,
+    url: &Url,
+) -> Result<Exchange, GrpcError>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let transport_err = |e: h2::Error| match e.get_io() {
+        Some(_) => TransportError::Connection(url.clone()),
+        None => TransportError::Other(format!("HTTP/2 error: {e}")),
+    };
+
+    let (client, connection) = h2::client::handshake(io).await.map_err(transport_err)?;
+
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            log::debug!("HTTP/2 connection closed with error: {e}");
+        }
+    });
+
+    let mut client = client.ready().await.map_err(transport_err)?;
+    let (response, mut stream) = client.send_request(request, false).map_err(transport_err)?;
+    stream.send_data(payload, true).map_err(transport_err)?;
+
+    let (parts, mut body) = response.await.map_err(transport_err)?.into_parts();
+
+    let mut buf = BytesMut::new();
+    while let Some(chunk) = body.data().await {
+        let chunk: Bytes = chunk.map_err(transport_err)?;
+        let _ = body.flow_control().release_capacity(chunk.len());
+        buf.extend_from_slice(&chunk);
+    }
+
+    let trailers: HeaderMap = body.trailers().await.map_err(transport_err)?.unwrap_or_default();
+
+    Ok((parts, buf.freeze(), trailers))
+}
+
+fn http_request(
+    url: &Url,
+    request: &GrpcRequest,
+    timeout: Duration,
+) -> Result<http::Request<()>, GrpcError> {
+    let uri: String = format!("{}://{}{}", url.scheme(), authority(url), request.path());
+    let mut http_request = http::Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .version(Version::HTTP_2)
+        .body(())
+        .map_err(|e| GrpcError::Other(e.to_string()))?;
+
+    let headers: &mut HeaderMap = http_request.headers_mut();
+    for (key, value) in request.headers.iter() {
+        if !CONNECTION_HEADERS.contains(&key.as_str()) {
+            headers.append(key, value.clone());
+        }
+    }
+
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(GRPC_CONTENT_TYPE));
+    headers.insert(TE, HeaderValue::from_static("trailers"));
+    headers.entry(USER_AGENT).or_insert(HeaderValue::from_static(crate::USER_AGENT));
+
+    let grpc_timeout = HeaderValue::from_str(&format!("{}m", timeout.as_millis()))
+        .map_err(|e| GrpcError::Other(e.to_string()))?;
+    headers.insert(HeaderName::from_static(GRPC_TIMEOUT_KEY), grpc_timeout);
+
+    Ok(http_request)
+}
+
+fn authority(url: &Url) -> String {
+    match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (Some(host), None) => host.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Map a gRPC status code to the HTTP status code that best describes it, using the same mapping
+/// as [grpc-gateway](https://github.com/grpc-ecosystem/grpc-gateway)
+fn http_status(code: u16) -> u16 {
+    match code {
+        0 => 200,
+        1 => 499,
+        3 | 9 | 11 => 400,
+        4 => 504,
+        5 => 404,
+        6 | 10 => 409,
+        7 => 403,
+        8 => 429,
+        12 => 501,
+        14 => 503,
+        16 => 401,
+        _ => 500,
+    }
+}
+
+#[derive(Debug)]
+pub enum GrpcError {
+    Descriptor(String),
+    UnknownMethod(String),
+    Streaming(String),
+    Message(String),
+    Transport(TransportError),
+    Other(String),
+}
+
+impl From<TransportError> for GrpcError {
+    fn from(e: TransportError) -> Self {
+        GrpcError::Transport(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use bytes::{Bytes, BytesMut};
+    use http::{HeaderMap, HeaderValue, Response};
+    use prost_reflect::prost::Message;
+    use prost_reflect::{DynamicMessage, MethodDescriptor, Value};
+    use tokio::net::TcpListener;
+
+    use super::{call, frame, unframe, GrpcRequest};
+
+    fn examples_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../examples")
+    }
+
+    /// Start a gRPC server that answers a single call to the `Greeter.SayHello` method
+    fn serve_once(method: MethodDescriptor) -> SocketAddr {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            runtime.block_on(async move {
+                let (socket, _) = listener.accept().await.unwrap();
+                let mut connection = h2::server::handshake(socket).await.unwrap();
+                let (request, mut respond) = connection.accept().await.unwrap().unwrap();
+                assert_eq!("/helloworld.Greeter/SayHello", request.uri().path());
+
+                let mut body = request.into_body();
+                let mut buf = BytesMut::new();
+                while let Some(chunk) = body.data().await {
+                    buf.extend_from_slice(&chunk.unwrap());
+                }
+
+                let hello = DynamicMessage::decode(method.input(), unframe(buf.freeze()).unwrap());
+                let name = hello.unwrap().get_field_by_name("name").unwrap().into_owned();
+                let name: &str = name.as_str().unwrap();
+
+                let mut reply = DynamicMessage::new(method.output());
+                reply.set_field_by_name("message", Value::String(format!("Hello {name}")));
+
+                let response = Response::builder()
+                    .header("content-type", "application/grpc")
+                    .body(())
+                    .unwrap();
+                let mut stream = respond.send_response(response, false).unwrap();
+                stream.send_data(frame(&reply.encode_to_vec()), false).unwrap();
+
+                let mut trailers = HeaderMap::new();
+                trailers.insert("grpc-status", HeaderValue::from_static("0"));
+                stream.send_trailers(trailers).unwrap();
+
+                // Drive the connection until the client hangs up
+                while connection.accept().await.is_some() {}
+            })
+        });
+
+        addr
+    }
+
+    fn request(addr: SocketAddr) -> GrpcRequest {
+        let input = format!(
+            r###"
+            url: http://{addr}
+            grpc:
+              proto:
+                - grpc/greeter.proto
+              service: helloworld.Greeter
+              method: SayHello
+              message:
+                name: fire
+            "###
+        );
+
+        let mut request = GrpcRequest::from_str(&input).unwrap();
+        request.resolve_paths(&examples_dir());
+        request
+    }
+
+    #[test]
+    fn test_frame_and_unframe_message() {
+        let message: Bytes = Bytes::from_static(b"\x0a\x04fire");
+        let framed: Bytes = frame(&message);
+        assert_eq!(&[0, 0, 0, 0, 6], &framed[..5]);
+        assert_eq!(message, unframe(framed).unwrap());
+    }
+
+    #[test]
+    fn test_unary_call() {
+        let method: MethodDescriptor =
+            request("127.0.0.1:0".parse().unwrap()).method_descriptor().unwrap();
+        let addr: SocketAddr = serve_once(method);
+
+        let response = call(request(addr), Duration::from_secs(5)).unwrap();
+
+        assert_eq!(200, response.status());
+        assert_eq!(Some("0"), response.header("grpc-status"));
+        assert_eq!(r#"{"message":"Hello fire"}"#, response.body());
+    }
+}
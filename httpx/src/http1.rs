@@ -0,0 +1,131 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::time::{Duration, Instant};
+
+use http::header::CONNECTION;
+use http::HeaderMap;
+use url::Url;
+
+use crate::{header, HttpRequest, HttpResponse};
+
+/// Write a request to a stream and read the response, using a plain HTTP/1.1 exchange where the
+/// connection is closed after the response, unless the request has its own `connection` header.
+/// Returns the response, the time from when the request was sent until the first byte of the
+/// response arrived, and the time it took to read the rest of the response.
+pub(crate) fn exchange<S: Read + Write>(
+    mut stream: S,
+    request: &HttpRequest,
+    url: &Url,
+) -> Result<(HttpResponse, Duration, Duration), std::io::Error> {
+    stream.write_all(&serialize(request, url))?;
+    stream.flush()?;
+    let sent: Instant = Instant::now();
+
+    let mut reader = BufReader::new(stream);
+    reader.fill_buf()?;
+    let first_byte: Instant = Instant::now();
+
+    let head_only: bool = request.method() == http::Method::HEAD;
+    let response: HttpResponse = read_response(reader, head_only)?;
+
+    Ok((response, first_byte - sent, first_byte.elapsed()))
+}
+
+fn serialize(request: &HttpRequest, url: &Url) -> Vec<u8> {
+    let target: String = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+
+    let mut head: String = format!("{} {} HTTP/1.1\r\n", request.method(), target);
+    for (key, value) in request.headers.iter() {
+        head.push_str(&format!("{}: {}\r\n", key, value.to_str().unwrap_or_default()));
+    }
+    if !request.headers.contains_key(CONNECTION) {
+        head.push_str("connection: close\r\n");
+    }
+    head.push_str("\r\n");
+
+    let mut bytes: Vec<u8> = head.into_bytes();
+    if let Some(body) = request.sent_body() {
+        bytes.extend_from_slice(body.as_bytes());
+    }
+
+    bytes
+}
+
+fn read_response<R: Read>(
+    mut reader: BufReader<R>,
+    head_only: bool,
+) -> Result<HttpResponse, std::io::Error> {
+    let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string());
+
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line)?;
+    let mut parts = status_line.trim_end().splitn(3, ' ');
+    let version: String = parts.next().unwrap_or_default().to_string();
+    let status: u16 = parts
+        .next()
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid("Invalid status line in response"))?;
+
+    let mut headers = HeaderMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line: &str = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            if let Ok((key, value)) = header(key.trim(), value.trim()) {
+                headers.append(key, value);
+            }
+        }
+    }
+
+    let header_value = |key: &str| headers.get(key).and_then(|v| v.to_str().ok());
+    let no_body: bool = head_only || status == 204 || status == 304 || (100..200).contains(&status);
+    let chunked: bool = header_value("transfer-encoding")
+        .map(|te| te.eq_ignore_ascii_case("chunked"))
+        .unwrap_or(false);
+    let content_length: Option<usize> =
+        header_value("content-length").and_then(|len| len.parse().ok());
+
+    let mut body: Vec<u8> = Vec::new();
+    if no_body {
+        // No body, regardless of any content-length
+    } else if chunked {
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size)?;
+            let size: &str = size.trim().split(';').next().unwrap_or_default();
+            let size: usize =
+                usize::from_str_radix(size, 16).map_err(|_| invalid("Invalid chunk size"))?;
+            if size == 0 {
+                break;
+            }
+            let mut chunk: Vec<u8> = vec![0; size];
+            reader.read_exact(&mut chunk)?;
+            body.extend(chunk);
+            // Each chunk is followed by a CRLF
+            reader.read_line(&mut String::new())?;
+        }
+    } else if let Some(len) = content_length {
+        body.resize(len, 0);
+        reader.read_exact(&mut body)?;
+    } else {
+        // Some TLS servers close the connection without notifying the client first, which is
+        // reported as an unexpected EOF, even though the whole response has been read
+        match reader.read_to_end(&mut body) {
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {}
+            result => {
+                result?;
+            }
+        }
+    }
+
+    let body: String = String::from_utf8_lossy(&body).to_string();
+    Ok(HttpResponse::new(version, status, headers, body))
+}
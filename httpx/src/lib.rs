@@ -1,10 +1,16 @@
+pub mod grpc;
+mod http1;
 pub mod request;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+mod timed;
+mod unix;
 
 extern crate http;
 extern crate serde;
 extern crate url;
 
-use std::{str::FromStr, time::Duration};
+use std::{path::PathBuf, str::FromStr, time::Duration};
 
 use http::{header::HeaderName, HeaderMap, HeaderValue, Method};
 use serde::Deserialize;
@@ -14,8 +20,9 @@ const USER_AGENT_KEY: &str = "user-agent";
 const USER_AGENT: &str = "fire/0.1.0";
 const CONTENT_LENGTH_KEY: &str = "content-length";
 const HOST_KEY: &str = "host";
+const UNIX_SCHEME: &str = "unix://";
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct HttpRequest {
     #[serde(alias = "verb")]
     #[serde(with = "http_serde::method")]
@@ -25,9 +32,20 @@ pub struct HttpRequest {
     #[serde(default)]
     #[serde(with = "http_serde::header_map")]
     headers: HeaderMap,
+    unix_socket: Option<PathBuf>,
 }
 
 impl HttpRequest {
+    pub fn new(method: Method, url: String, headers: HeaderMap, body: Option<String>) -> Self {
+        HttpRequest {
+            method,
+            url,
+            body,
+            headers,
+            unix_socket: None,
+        }
+    }
+
     pub fn method(&self) -> Method {
         self.method.clone()
     }
@@ -35,15 +53,49 @@ impl HttpRequest {
     pub fn url(&self) -> Result<Url, url::ParseError> {
         if self.url.starts_with("http://") || self.url.starts_with("https://") {
             Url::parse(&self.url)
+        } else if let Some((_, path)) = self.unix_url() {
+            Url::parse(&format!("http://localhost/{path}"))
         } else {
             Url::parse(&format!("https://{}", &self.url))
         }
     }
 
+    /// Path to the Unix domain socket that the request should be sent over, if any. This is either
+    /// given explicitly with `unix_socket`, or as part of an URL in the form
+    /// `unix:///path/to/socket.sock:/http/path`.
+    pub fn unix_socket(&self) -> Option<PathBuf> {
+        match (&self.unix_socket, self.unix_url()) {
+            (Some(socket), _) => Some(socket.clone()),
+            (None, Some((socket, _))) => Some(PathBuf::from(socket)),
+            (None, None) => None,
+        }
+    }
+
+    fn unix_url(&self) -> Option<(&str, &str)> {
+        let url: &str = self.url.strip_prefix(UNIX_SCHEME)?;
+        match url.split_once(':') {
+            Some((socket, path)) => Some((socket, path.trim_start_matches('/'))),
+            None => Some((url, "")),
+        }
+    }
+
+    /// URL of the request as it was given, which may be relative to a base URL
+    pub fn raw_url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn set_url(&mut self, url: String) {
+        self.url = url;
+    }
+
     pub fn headers(&self) -> HeaderMap {
         self.headers.clone()
     }
 
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
     pub fn header(&self, key: &str) -> Option<&str> {
         let key = HeaderName::from_str(key).ok()?;
         self.headers.get(key).and_then(|v| v.to_str().ok())
@@ -84,6 +136,12 @@ impl HttpRequest {
         &self.body
     }
 
+    /// The body that is sent with the request, which is only the body of a request with a method
+    /// that has a body, such as `POST`
+    pub fn sent_body(&self) -> Option<&str> {
+        self.body.as_deref().filter(|_| self.has_body())
+    }
+
     pub fn body_size(&self) -> usize {
         match self.method {
             Method::PUT | Method::POST | Method::DELETE | Method::PATCH => match &self.body {
@@ -121,9 +179,16 @@ impl FromStr for HttpRequest {
 
 impl From<HttpRequest> for (ureq::Request, Option<String>) {
     fn from(req: HttpRequest) -> Self {
-        let url = req.url().unwrap();
-        let request: ureq::Request = req.headers.iter().fold(
-            ureq::request(req.method.as_ref(), url.as_str()),
+        req.to_ureq(&ureq::agent())
+    }
+}
+
+impl HttpRequest {
+    /// Request that is sent with `agent`, and the body to send with it
+    pub(crate) fn to_ureq(&self, agent: &ureq::Agent) -> (ureq::Request, Option<String>) {
+        let url = self.url().unwrap();
+        let request: ureq::Request = self.headers.iter().fold(
+            agent.request(self.method.as_ref(), url.as_str()),
             |r, (key, value)| {
                 let key: &str = key.as_str();
                 let value: &str = match value.to_str() {
@@ -134,7 +199,7 @@ impl From<HttpRequest> for (ureq::Request, Option<String>) {
             },
         );
 
-        (request, req.body().clone())
+        (request, self.sent_body().map(String::from))
     }
 }
 
@@ -143,9 +208,50 @@ pub struct HttpResponse {
     status: u16,
     headers: HeaderMap,
     body: String,
+    timings: Option<Timings>,
+}
+
+/// Time spent in each phase of a request. Only available for requests that are sent with
+/// [request::call_with_timings].
+#[derive(Debug, Clone, Copy)]
+pub struct Timings {
+    /// Resolving the host name, if a lookup was made
+    pub dns: Option<Duration>,
+    /// Establishing the connection, if a new connection was made
+    pub connect: Option<Duration>,
+    /// TLS handshake, if the connection is encrypted
+    pub tls: Option<Duration>,
+    /// From when the request was sent, until the first byte of the response arrived
+    pub first_byte: Duration,
+    /// Reading the rest of the response
+    pub transfer: Duration,
+}
+
+impl Timings {
+    pub fn total(&self) -> Duration {
+        self.dns.unwrap_or_default()
+            + self.connect.unwrap_or_default()
+            + self.tls.unwrap_or_default()
+            + self.first_byte
+            + self.transfer
+    }
 }
 
 impl HttpResponse {
+    pub fn new(version: String, status: u16, headers: HeaderMap, body: String) -> Self {
+        HttpResponse {
+            version,
+            status,
+            headers,
+            body,
+            timings: None,
+        }
+    }
+
+    pub fn timings(&self) -> Option<&Timings> {
+        self.timings.as_ref()
+    }
+
     pub fn version(&self) -> &str {
         &self.version
     }
@@ -191,6 +297,7 @@ impl From<ureq::Response> for HttpResponse {
             status: resp.status(),
             headers,
             body: resp.into_string().unwrap_or_default(),
+            timings: None,
         }
     }
 }
@@ -233,6 +340,7 @@ mod tests {
         request.set_default_headers().unwrap();
 
         assert_eq!(Method::POST, request.method());
+        assert_eq!(None, request.unix_socket());
 
         let expected_url = Url::parse("https://api.github.com/markdown").unwrap();
         let actual_url = request.url().unwrap();
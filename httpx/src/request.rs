@@ -4,22 +4,59 @@ use url::Url;
 use crate::{HttpRequest, HttpResponse, TransportError};
 
 pub fn call(request: HttpRequest, timeout: Duration) -> Result<HttpResponse, TransportError> {
+    send(&ureq::agent(), request, timeout)
+}
+
+/// Client that keeps connections open between requests, so that a request which is sent several
+/// times, such as in a load test, does not open a new connection every time
+pub struct Client {
+    agent: ureq::Agent,
+}
+
+impl Client {
+    /// Create a client that keeps up to `connections` idle connections open to each host
+    pub fn new(connections: usize) -> Client {
+        let agent: ureq::Agent =
+            ureq::AgentBuilder::new().max_idle_connections_per_host(connections).build();
+        Client { agent }
+    }
+
+    pub fn call(
+        &self,
+        request: HttpRequest,
+        timeout: Duration,
+    ) -> Result<HttpResponse, TransportError> {
+        send(&self.agent, request, timeout)
+    }
+}
+
+fn send(
+    agent: &ureq::Agent,
+    request: HttpRequest,
+    timeout: Duration,
+) -> Result<HttpResponse, TransportError> {
+    if let Some(socket) = request.unix_socket() {
+        return crate::unix::call(request, &socket, timeout);
+    }
+
     let url: Url = request.url().unwrap();
-    let (request, body): (ureq::Request, Option<String>) = request.into();
+    let (request, body): (ureq::Request, Option<String>) = request.to_ureq(agent);
     let request = request.timeout(timeout);
 
-    let response: Result<ureq::Response, ureq::Error> = match body {
+    let res: Result<ureq::Response, ureq::Error> = match body {
         Some(body) => request.send_string(&body),
         None => request.call(),
     };
 
-    conv(response, url)
+    Ok(response(res, url)?.into())
 }
 
-fn conv(
+/// The response of a request, also when it has an error status, or the error that prevented the
+/// request from completing
+pub(crate) fn response(
     res: Result<ureq::Response, ureq::Error>,
     url: Url,
-) -> Result<HttpResponse, TransportError> {
+) -> Result<ureq::Response, TransportError> {
     let response: ureq::Response = match res {
         Ok(response) => response,
         Err(e) => match e {
@@ -38,6 +75,17 @@ fn conv(
         },
     };
 
-    let response: HttpResponse = response.into();
     Ok(response)
 }
+
+/// Send a request where the time spent in each phase of the request is measured, which is then
+/// available from [HttpResponse::timings]
+pub fn call_with_timings(
+    request: HttpRequest,
+    timeout: Duration,
+) -> Result<HttpResponse, TransportError> {
+    match request.unix_socket() {
+        Some(socket) => crate::unix::call(request, &socket, timeout),
+        None => crate::timed::call(request, timeout),
+    }
+}
@@ -0,0 +1,199 @@
+use std::io::{Error, ErrorKind};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ureq::rustls::version::{TLS12, TLS13};
+use ureq::rustls::{ClientConfig, RootCertStore};
+use ureq::{ReadWrite, TlsConnector};
+use url::{Host, Url};
+
+use crate::{HttpRequest, HttpResponse, Timings, TransportError};
+
+/// Send a request with ureq, in the same way as [crate::request::call], where the DNS lookup and
+/// the TLS handshake are timed by hooks in the agent. ureq does not tell when a plain TCP
+/// connection is established, so without TLS the connection is made and timed by the resolver,
+/// see [relay]. When redirects are followed, the time spent on them is part of the time to first
+/// byte, while the other phases are those of the first connection.
+pub fn call(request: HttpRequest, timeout: Duration) -> Result<HttpResponse, TransportError> {
+    let url: Url = request.url().unwrap();
+    let marks: Marks = Marks::default();
+
+    let resolver_marks: Marks = marks.clone();
+    let plain: bool = url.scheme() == "http";
+    let agent: ureq::Agent = ureq::AgentBuilder::new()
+        .resolver(move |netloc: &str| {
+            let addrs: Vec<SocketAddr> = netloc.to_socket_addrs()?.collect();
+            let mut phases = resolver_marks.lock().unwrap();
+            phases.connections += 1;
+            phases.resolved.get_or_insert_with(Instant::now);
+            if plain && phases.connections == 1 {
+                let stream: TcpStream = connect(&addrs, timeout)?;
+                phases.connected = Some(Instant::now());
+                return Ok(vec![relay(stream)?]);
+            }
+            Ok(addrs)
+        })
+        .tls_connector(Arc::new(TimedTls {
+            config: tls_config(),
+            marks: marks.clone(),
+        }))
+        .build();
+
+    let (request, body): (ureq::Request, Option<String>) = request.to_ureq(&agent);
+    let request = request.timeout(timeout);
+
+    let start: Instant = Instant::now();
+    let response: Result<ureq::Response, ureq::Error> = match body {
+        Some(body) => request.send_string(&body),
+        None => request.call(),
+    };
+    let first_byte: Instant = Instant::now();
+    let mut response: HttpResponse = crate::request::response(response, url.clone())?.into();
+    let transfer: Duration = first_byte.elapsed();
+
+    let phases = marks.lock().unwrap();
+    let resolved: Instant = phases.resolved.unwrap_or(start);
+    let ready: Instant = phases.handshaken.or(phases.connected).unwrap_or(resolved);
+    response.timings = Some(Timings {
+        dns: matches!(url.host(), Some(Host::Domain(_))).then(|| resolved - start),
+        connect: phases.connected.map(|connected| connected - resolved),
+        tls: phases.handshaken.zip(phases.connected).map(|(done, started)| done - started),
+        first_byte: first_byte - ready,
+        transfer,
+    });
+
+    Ok(response)
+}
+
+/// When the phases of the first connection of a request ended, as recorded by the hooks
+#[derive(Default)]
+struct Phases {
+    connections: usize,
+    resolved: Option<Instant>,
+    connected: Option<Instant>,
+    handshaken: Option<Instant>,
+}
+
+type Marks = Arc<Mutex<Phases>>;
+
+/// Connect to the first address that accepts a connection, in the same way as ureq
+fn connect(addrs: &[SocketAddr], timeout: Duration) -> Result<TcpStream, Error> {
+    let mut error = Error::new(ErrorKind::NotFound, "No address found for host");
+    for addr in addrs {
+        match TcpStream::connect_timeout(addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+/// Forward a local connection to a connection that is already established, so that ureq sends
+/// the request over the connection that was timed by the resolver. Returns the local address that
+/// ureq should connect to. The `host` header is taken from the URL, so it is not changed.
+fn relay(upstream: TcpStream) -> Result<SocketAddr, Error> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let addr: SocketAddr = listener.local_addr()?;
+
+    std::thread::spawn(move || -> Result<(), Error> {
+        let (local, _) = listener.accept()?;
+        let (mut local_read, mut upstream_write) = (local.try_clone()?, upstream.try_clone()?);
+        let forward = std::thread::spawn(move || {
+            let _ = std::io::copy(&mut local_read, &mut upstream_write);
+            // Once ureq closes its connection, so is the connection to the server
+            let _ = upstream_write.shutdown(Shutdown::Both);
+        });
+        let (mut local, mut upstream) = (local, upstream);
+        let _ = std::io::copy(&mut upstream, &mut local);
+        let _ = local.shutdown(Shutdown::Both);
+        let _ = forward.join();
+        Ok(())
+    });
+
+    Ok(addr)
+}
+
+/// TLS connector that does the same handshake as the default connector of ureq, and records when
+/// the TCP connection was established and when the handshake was completed
+struct TimedTls {
+    config: Arc<ClientConfig>,
+    marks: Marks,
+}
+
+impl TlsConnector for TimedTls {
+    fn connect(
+        &self,
+        dns_name: &str,
+        io: Box<dyn ReadWrite>,
+    ) -> Result<Box<dyn ReadWrite>, ureq::Error> {
+        let first: bool = self.marks.lock().unwrap().connections == 1;
+        let connected: Instant = Instant::now();
+        let stream: Box<dyn ReadWrite> = self.config.connect(dns_name, io)?;
+        if first {
+            let mut phases = self.marks.lock().unwrap();
+            phases.connected = Some(connected);
+            phases.handshaken = Some(Instant::now());
+        }
+        Ok(stream)
+    }
+}
+
+/// The same TLS configuration as the default configuration of ureq
+fn tls_config() -> Arc<ClientConfig> {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config: ClientConfig =
+        ClientConfig::builder_with_provider(ureq::rustls::crypto::ring::default_provider().into())
+            .with_protocol_versions(&[&TLS12, &TLS13])
+            .expect("ring supports TLS 1.2 and 1.3")
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    Arc::new(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use crate::HttpRequest;
+
+    #[test]
+    fn test_call_with_timings() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port: u16 = listener.local_addr().unwrap().port();
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = String::new();
+            while reader.read_line(&mut head).unwrap() > 2 {}
+            std::thread::sleep(Duration::from_millis(50));
+            let response = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+            stream.write_all(response.as_bytes()).unwrap();
+            head.to_lowercase()
+        });
+
+        let input = format!("method: GET\nurl: http://localhost:{port}/");
+        let request = HttpRequest::from_str(&input).unwrap();
+        let response = super::call(request, Duration::from_secs(5)).unwrap();
+        let head: String = server.join().unwrap();
+
+        // The request is sent by ureq, with the same headers as any other request
+        assert!(head.contains("user-agent: ureq/"));
+        assert!(!head.contains("connection:"));
+
+        let timings = response.timings().unwrap();
+        assert_eq!(200, response.status());
+        assert_eq!("ok", response.body());
+        assert!(timings.dns.is_some());
+        assert!(timings.connect.is_some());
+        assert!(timings.tls.is_none());
+        assert!(timings.first_byte >= Duration::from_millis(50));
+        assert!(timings.total() >= timings.first_byte);
+    }
+}
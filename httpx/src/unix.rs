@@ -0,0 +1,107 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+use crate::{HttpRequest, HttpResponse, Timings, TransportError};
+
+/// Send a request over a Unix domain socket, using a plain HTTP/1.1 exchange. The connection is
+/// closed after the response has been read, so no connection pooling is done.
+#[cfg(unix)]
+pub fn call(
+    request: HttpRequest,
+    socket: &Path,
+    timeout: Duration,
+) -> Result<HttpResponse, TransportError> {
+    use std::os::unix::net::UnixStream;
+
+    let url: Url = request.url().unwrap();
+    let io_err = |e: std::io::Error| match e.kind() {
+        std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => {
+            TransportError::Timeout(url.clone(), timeout)
+        }
+        _ => TransportError::Other(format!("{}: {e}", socket.display())),
+    };
+
+    let start: Instant = Instant::now();
+    let stream: UnixStream =
+        UnixStream::connect(socket).map_err(|_| TransportError::Connection(url.clone()))?;
+    let connect: Duration = start.elapsed();
+    stream.set_read_timeout(Some(timeout)).map_err(io_err)?;
+    stream.set_write_timeout(Some(timeout)).map_err(io_err)?;
+
+    let (mut response, first_byte, transfer) =
+        crate::http1::exchange(stream, &request, &url).map_err(io_err)?;
+
+    response.timings = Some(Timings {
+        dns: None,
+        connect: Some(connect),
+        tls: None,
+        first_byte,
+        transfer,
+    });
+
+    Ok(response)
+}
+
+#[cfg(not(unix))]
+pub fn call(
+    _request: HttpRequest,
+    _socket: &Path,
+    _timeout: Duration,
+) -> Result<HttpResponse, TransportError> {
+    Err(TransportError::Other(String::from(
+        "Unix domain sockets are not supported on this platform",
+    )))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixListener;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use crate::test_util::TempDir;
+    use crate::HttpRequest;
+
+    #[test]
+    fn test_call_over_unix_socket() {
+        let dir = TempDir::new("fire-unix");
+        let socket: PathBuf = dir.join("test.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head: Vec<String> = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim_end().is_empty() {
+                    break;
+                }
+                head.push(line.trim_end().to_string());
+            }
+            let response = "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                transfer-encoding: chunked\r\n\r\n6\r\n{\"ok\":\r\n5\r\ntrue}\r\n0\r\n\r\n";
+            stream.write_all(response.as_bytes()).unwrap();
+            head
+        });
+
+        let input = format!("method: GET\nurl: unix://{}:/containers/json?all=1", socket.display());
+        let mut request = HttpRequest::from_str(&input).unwrap();
+        request.set_default_headers().unwrap();
+        assert_eq!(Some(socket.clone()), request.unix_socket());
+
+        let response = super::call(request, &socket, Duration::from_secs(5)).unwrap();
+        let head: Vec<String> = server.join().unwrap();
+
+        assert_eq!("GET /containers/json?all=1 HTTP/1.1", head[0]);
+        assert!(head.contains(&String::from("host: localhost")));
+        assert_eq!(200, response.status());
+        assert_eq!(Some("application/json"), response.header("content-type"));
+        assert_eq!(r#"{"ok":true}"#, response.body());
+    }
+}
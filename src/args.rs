@@ -1,26 +1,38 @@
 use std::{
+    io::IsTerminal,
     path::{Path, PathBuf},
+    str::FromStr,
     time::Duration,
 };
 
-use clap::Parser;
+use clap::{ArgGroup, Parser, Subcommand};
 use termcolor::ColorChoice;
 use walkdir::WalkDir;
 
+use crate::config::{self, Colors, Config, Pager};
+use crate::error::FireError;
+use crate::export::Export;
+use crate::loadtest;
+use crate::output::Format;
+use crate::pager::Paging;
 use crate::prop::{self, ParsePropertyError, Property};
+use crate::query::Query;
+use crate::snapshot;
 
 const BANNER: &str = include_str!("../resources/banner");
 const ABOUT: &str = include_str!("../resources/about");
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = ABOUT, before_long_help = BANNER)]
+#[clap(subcommand_negates_reqs = true)]
+#[clap(group(ArgGroup::new("load").multiple(true).args(&["repeat", "duration"])))]
 pub struct Args {
     /// Set verbosity level, 0 - 5
     ///
     /// Set the verbosity level, from 0 (least amount of output) to 5 (most verbose). Note that
-    /// logging level configured via RUST_LOG overrides this setting.
-    #[clap(short = 'v', long = "verbosity", default_value = "1")]
-    pub verbosity_level: u8,
+    /// logging level configured via RUST_LOG overrides this setting. Defaults to 1.
+    #[clap(short = 'v', long = "verbosity", global = true)]
+    verbosity_level: Option<u8>,
 
     /// Print debug information
     ///
@@ -33,34 +45,34 @@ pub struct Args {
     ///
     /// Enable output with colors. By default colors will be used if the terminal seems
     /// to support colors.
-    #[clap(short = 'c', long = "colors")]
+    #[clap(short = 'c', long = "colors", global = true)]
     enable_colors: bool,
 
     /// Disable colors
     ///
     /// Disable output with colors. By default colors will be used if the terminal seems
     /// to support colors.
-    #[clap(short = 'C', long = "no-colors")]
+    #[clap(short = 'C', long = "no-colors", global = true)]
     disable_colors: bool,
 
     /// Show headers
     ///
     /// Print headers
-    #[clap(short = 'H', long)]
+    #[clap(short = 'H', long, global = true)]
     headers: bool,
 
     /// Print request
     ///
     /// Print the content of the request as it is sent to the remote host. To also see request
     /// headers, use the `--headers` flag (`-H`).
-    #[clap(short, long)]
+    #[clap(short, long, global = true)]
     request: bool,
 
     /// Ask for confirmation
     ///
     /// Ask for confirmation before executing a request. This implies `--request` and `--headers`
     /// since the request will have to be displayed before it can be confirmed.
-    #[clap(short, long)]
+    #[clap(short, long, global = true)]
     ask: bool,
 
     /// Interactive request
@@ -82,6 +94,134 @@ pub struct Args {
     #[clap(short, long)]
     pub trim: bool,
 
+    /// Export request as command
+    ///
+    /// Print the request as a command for another HTTP client instead of executing it. The request
+    /// is rendered exactly as it would be sent, with template variables substituted and default
+    /// headers added, and every argument is quoted so the command can be pasted into a shell.
+    #[clap(long = "as", value_enum, value_name = "CLIENT")]
+    export: Option<Export>,
+
+    /// Write exchange to HAR file
+    ///
+    /// Append the executed request, its response and timings to a HAR (HTTP Archive) file, which
+    /// is created if it does not exist. The file can then be shared, or opened in tools that
+    /// supports HAR files, such as the developer tools of most browsers.
+    #[clap(long, value_name = "PATH", global = true)]
+    har: Option<PathBuf>,
+
+    /// Output format
+    ///
+    /// Format of the output. With `json`, the rendered request, the response, the duration and the
+    /// environments that were used are printed as a single JSON object, where bodies with a JSON
+    /// content type are included as JSON. This is useful for scripting, such as piping the output
+    /// to `jq`.
+    #[clap(long, value_enum, default_value = "terminal", global = true)]
+    format: Format,
+
+    /// Filter JSON response
+    ///
+    /// Only print the part of a JSON response body that matches a query, such as
+    /// `.data.items[0].id`. Keys are separated by `.`, array elements are selected with `[0]` (or
+    /// `[-1]` for the last element) and `[*]` selects all elements. The result is formatted as any
+    /// other JSON body.
+    #[clap(short, long, value_name = "QUERY", value_parser = Query::from_str, global = true)]
+    query: Option<Query>,
+
+    /// Show timings
+    ///
+    /// Show how long each phase of the request took, as a waterfall under the status line: DNS
+    /// lookup, TCP connect, TLS handshake, time to first byte and transfer of the response.
+    #[clap(long, global = true)]
+    timings: bool,
+
+    /// Print only the body
+    ///
+    /// Print only the body of the response, formatted as usual, without the status line, the
+    /// border or any headers. When stdout is not a terminal, such as when the output is piped to
+    /// another program or redirected to a file, everything but the body is written to stderr
+    /// instead, so this is only needed to get rid of it entirely.
+    #[clap(short, long, global = true)]
+    body: bool,
+
+    /// Print the raw body
+    ///
+    /// Print only the body of the response exactly as it was received, without any formatting or
+    /// syntax highlighting. This implies `--body`.
+    #[clap(long, global = true)]
+    raw: bool,
+
+    /// Theme for syntax highlighting
+    ///
+    /// Name of the theme used for syntax highlighting of bodies. If not set, the theme in
+    /// `$FIRE_THEME` is used, or `base16-mocha.dark` if that is not set either. Custom themes can
+    /// be added as `.tmTheme` files in `~/.config/fire/themes`. Run `fire themes` to preview all
+    /// available themes.
+    #[clap(long, value_name = "NAME", global = true)]
+    theme: Option<String>,
+
+    /// Always use a pager
+    ///
+    /// Show the response in a pager whenever stdout is a terminal, rather than only when the
+    /// response is taller than the terminal. The pager is `$FIRE_PAGER`, or `$PAGER` if it is not
+    /// set, or `less -R` if neither is set. Setting `FIRE_PAGER` to an empty value disables the
+    /// pager.
+    #[clap(long, global = true)]
+    pager: bool,
+
+    /// Never use a pager
+    ///
+    /// Print the response directly, even when it is taller than the terminal.
+    #[clap(long, global = true, conflicts_with = "pager")]
+    no_pager: bool,
+
+    /// Compare response with snapshot
+    ///
+    /// Compare the response with a snapshot of the response, which is stored next to the request
+    /// file as `<name>.snapshot.json`. The snapshot contains the status, the `content-type` header
+    /// and the body of the response, and is created the first time the request is executed. Any
+    /// differences from the snapshot are printed, and the exit code is non-zero.
+    #[clap(long, global = true)]
+    snapshot: bool,
+
+    /// Update snapshot
+    ///
+    /// Replace the snapshot of the response with the current response. This implies `--snapshot`.
+    #[clap(long, global = true)]
+    update_snapshot: bool,
+
+    /// Ignore path in snapshot
+    ///
+    /// Ignore a field in the snapshot which is expected to change between requests, such as a
+    /// timestamp or an id. Paths are separated by dots, where `*` matches any field or array
+    /// element, like `body.items.*.created_at` or `headers.etag`. Ignored paths are saved in the
+    /// snapshot, so they only need to be given once. Can be given several times.
+    #[clap(long, value_name = "PATH", global = true)]
+    snapshot_ignore: Vec<String>,
+
+    /// Include header in snapshot
+    ///
+    /// Include a response header in the snapshot, in addition to `content-type`. Can be given
+    /// several times.
+    #[clap(long, value_name = "NAME", global = true)]
+    snapshot_header: Vec<String>,
+
+    /// Save exchange to history
+    ///
+    /// Save the executed request and its response to the history, which is stored in
+    /// `$XDG_DATA_HOME/fire` (or `~/.local/share/fire`). The history is only saved when this flag
+    /// is given, or when `history = true` is set in the configuration, since the saved requests
+    /// are fully rendered and may contain secrets such as tokens from `.sec` files.
+    #[clap(long, global = true)]
+    history: bool,
+
+    /// Do not save exchange to history
+    ///
+    /// Do not save the exchange to the history, even when `history = true` is set in the
+    /// configuration.
+    #[clap(long, global = true, conflicts_with = "history")]
+    no_history: bool,
+
     /// Environments
     ///
     /// One or several environments which containins environment variables. If the environment is
@@ -108,23 +248,253 @@ pub struct Args {
 
     /// Request timeout
     ///
-    /// Max time to wait, in seconds, before request times out
-    #[clap(short = 'T', long = "timeout", default_value = "30")]
-    timeout: usize,
+    /// Max time to wait, in seconds, before request times out. Defaults to 30 seconds.
+    #[clap(short = 'T', long = "timeout", global = true)]
+    timeout: Option<usize>,
+
+    /// Select request in file
+    ///
+    /// Name of the request to execute, when the request file is a `.http` file with more than one
+    /// request. A request is named either by the text after its `###` separator, or with a
+    /// `# @name` comment. Requests can also be selected by their position in the file, starting
+    /// at 1.
+    #[clap(short, long)]
+    name: Option<String>,
+
+    /// Repeat request
+    ///
+    /// Execute the request this many times, and report throughput, status codes, errors and
+    /// latency percentiles instead of printing the responses
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    repeat: Option<u64>,
+
+    /// Concurrent requests
+    ///
+    /// Number of requests that are executed at the same time, when the request is repeated with
+    /// `--repeat` or `--duration`
+    #[clap(long, value_name = "N", default_value = "1", requires = "load", value_parser = clap::value_parser!(u64).range(1..))]
+    concurrency: u64,
+
+    /// Repeat request for a duration
+    ///
+    /// Execute the request repeatedly until this much time has passed, such as `30s`, `5m` or
+    /// `500ms`. When combined with `--repeat`, requests stop when either limit is reached.
+    #[clap(long, value_name = "DURATION", value_parser = parse_duration)]
+    duration: Option<Duration>,
 
     /// Request file
     ///
-    /// Request template file which contains the request that should be executed
-    #[clap(value_parser)]
-    file: PathBuf,
+    /// Request template file which contains the request that should be executed. This is either a
+    /// YAML file, or a `.http` file in the format used by REST Client and the JetBrains HTTP
+    /// Client.
+    #[clap(value_parser, required = true)]
+    file: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    /// Default values from configuration files, for options not given on the command line
+    #[clap(skip)]
+    config: Config,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Import requests from other formats
+    ///
+    /// Create request files from requests in other formats, such as curl commands
+    #[clap(subcommand)]
+    Import(Import),
+
+    /// Show history of executed requests
+    ///
+    /// List previously executed requests, with the most recent request last. Entries can be
+    /// printed again with `history show <ID>`, or executed again with `history replay <ID>`.
+    History {
+        #[clap(subcommand)]
+        command: Option<History>,
+
+        /// Only list entries where the URL, request file or status matches this search term
+        #[clap(short, long)]
+        search: Option<String>,
+
+        /// Max number of entries to list
+        #[clap(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// Preview themes for syntax highlighting
+    ///
+    /// Print a sample JSON body highlighted with each available theme, including custom themes
+    /// in `~/.config/fire/themes`. A theme is selected with `--theme` or `$FIRE_THEME`.
+    Themes,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum History {
+    /// Print an entry in the history
+    ///
+    /// Print the request and response of an entry in the history, as they were printed when the
+    /// request was executed. Use `--headers` (`-H`) to also print headers.
+    Show {
+        /// Id of the entry
+        id: u64,
+    },
+
+    /// Execute the request of an entry in the history again
+    ///
+    /// Execute the exact same request as in an entry in the history, without rendering the
+    /// request file again. The new exchange is saved as a new entry in the history.
+    Replay {
+        /// Id of the entry
+        id: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Import {
+    /// Import a curl command
+    ///
+    /// Create a request file from a curl command. The command is read from stdin if it is not
+    /// given as an argument.
+    Curl {
+        /// Curl command
+        ///
+        /// Curl command to import, such as `curl -X POST https://example.com -d 'foo=bar'`
+        command: Option<String>,
+
+        /// Output file
+        ///
+        /// Write the request file to this path instead of to stdout
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+
+        /// Use template variables for secrets
+        ///
+        /// Replace the values of headers that seem to contain secrets, such as `authorization`,
+        /// with template variables, so the secrets can be kept in a `.sec` file instead
+        #[clap(short = 's', long)]
+        template_secrets: bool,
+    },
+
+    /// Import a HAR file
+    ///
+    /// Create one request file for each entry in a HAR (HTTP Archive) file, such as a file exported
+    /// from the developer tools of a browser
+    Har {
+        /// HAR file
+        file: PathBuf,
+
+        /// Only import requests to this host
+        #[clap(long)]
+        host: Option<String>,
+
+        /// Output directory
+        ///
+        /// Directory where the request files are written
+        #[clap(short, long, default_value = ".")]
+        output_dir: PathBuf,
+
+        /// Use template variables for secrets
+        ///
+        /// Replace the values of headers that seem to contain secrets, such as `authorization`,
+        /// with template variables, so the secrets can be kept in a `.sec` file instead
+        #[clap(short = 's', long)]
+        template_secrets: bool,
+    },
+
+    /// Import a Postman collection
+    ///
+    /// Create request files from a Postman collection (v2.1), where folders in the collection are
+    /// mirrored as directories. Collection variables are written to a `.env` file.
+    Postman {
+        /// Postman collection file
+        collection: PathBuf,
+
+        /// Postman environment file
+        ///
+        /// Postman environment to convert into an environments file, `<name>.env`, which can then
+        /// be used with the `--env` flag. Variables of the type `secret` are written to
+        /// `<name>.sec` instead. Can be given several times.
+        #[clap(short, long)]
+        environment: Vec<PathBuf>,
+
+        /// Output directory
+        ///
+        /// Directory where the request files and environments files are written
+        #[clap(short, long, default_value = ".")]
+        output_dir: PathBuf,
+    },
+
+    /// Import an OpenAPI specification
+    ///
+    /// Create a request file for each operation in an OpenAPI 3 specification (JSON or YAML),
+    /// grouped in directories by tag. Path parameters, required query parameters and required
+    /// headers become template variables, and request bodies are filled in with examples. A `.env`
+    /// file listing all variables is also written, unless it already exists.
+    Openapi {
+        /// OpenAPI specification file
+        spec: PathBuf,
+
+        /// Output directory
+        ///
+        /// Directory where the request files and environments file are written
+        #[clap(short, long, default_value = ".")]
+        output_dir: PathBuf,
+    },
+}
+
+/// Parse a duration such as `30s`, `5m`, `500ms` or just `30` (seconds)
+fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input: &str = input.trim();
+    let split: usize = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    let (value, unit) = input.split_at(split);
+    let value: u64 = value.parse().map_err(|_| format!("Invalid duration '{input}'"))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(value)),
+        "" | "s" => Ok(Duration::from_secs(value)),
+        "m" => Ok(Duration::from_secs(value * 60)),
+        "h" => Ok(Duration::from_secs(value * 3600)),
+        _ => Err(format!("Invalid unit '{unit}' in duration, expected ms, s, m or h")),
+    }
 }
 
 impl Args {
+    /// Load configuration files for the request file, or for the current directory if no request
+    /// file is given, which provide default values for options not given on the command line
+    pub fn load_config(&mut self) -> Result<(), FireError> {
+        let dir: PathBuf = match self.file.as_deref().and_then(|file| file.canonicalize().ok()) {
+            Some(file) => file.parent().unwrap().to_path_buf(),
+            None => std::env::current_dir().map_err(|e| FireError::GenericIO(e.to_string()))?,
+        };
+        self.config = config::load(&dir)?;
+        Ok(())
+    }
+
+    pub fn verbosity(&self) -> u8 {
+        self.verbosity_level.or(self.config.verbosity).unwrap_or(1)
+    }
+
+    /// Colors for stdout, where colors are never used by default if stdout is not a terminal
     pub fn use_colors(&self) -> ColorChoice {
+        self.colors_for(std::io::stdout().is_terminal())
+    }
+
+    /// Colors for stderr, where colors are never used by default if stderr is not a terminal
+    pub fn stderr_colors(&self) -> ColorChoice {
+        self.colors_for(std::io::stderr().is_terminal())
+    }
+
+    fn colors_for(&self, terminal: bool) -> ColorChoice {
         match (self.enable_colors, self.disable_colors) {
             (true, false) => ColorChoice::Always,
             (false, true) => ColorChoice::Never,
-            (false, false) => ColorChoice::Auto,
+            (false, false) => match self.config.colors {
+                Some(Colors::Always) => ColorChoice::Always,
+                Some(Colors::Never) => ColorChoice::Never,
+                Some(Colors::Auto) | None if terminal => ColorChoice::Auto,
+                Some(Colors::Auto) | None => ColorChoice::Never,
+            },
             (true, true) => {
                 panic!("Flags --colors (-c) and --no-colors (-C) are mutually exclusive")
             }
@@ -132,18 +502,20 @@ impl Args {
     }
 
     pub fn try_colors(&self) -> bool {
-        match self.use_colors() {
-            ColorChoice::Never => false,
-            _ => true,
-        }
+        !matches!(self.use_colors(), ColorChoice::Never)
     }
 
     pub fn file(&self) -> &std::path::Path {
-        &self.file
+        self.file
+            .as_deref()
+            .expect("Request file is required when no subcommand is given")
     }
 
-    pub fn timeout(&self) -> Duration {
-        Duration::from_secs(self.timeout as u64)
+    /// Timeout for a request, where a timeout given for the request, such as from `_defaults.yml`,
+    /// takes precedence over the configuration but not over `--timeout`
+    pub fn timeout(&self, request_timeout: Option<usize>) -> Duration {
+        let timeout: usize = self.timeout.or(request_timeout).or(self.config.timeout).unwrap_or(30);
+        Duration::from_secs(timeout as u64)
     }
 
     pub fn print_request(&self) -> bool {
@@ -151,7 +523,7 @@ impl Args {
     }
 
     pub fn print_headers(&self) -> bool {
-        self.headers || self.ask()
+        self.headers || self.config.headers.unwrap_or(false) || self.ask()
     }
 
     pub fn ask(&self) -> bool {
@@ -162,6 +534,105 @@ impl Args {
         self.interactive
     }
 
+    pub fn har(&self) -> Option<&Path> {
+        self.har.as_deref()
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn snapshot(&self) -> Option<snapshot::Options<'_>> {
+        match self.snapshot || self.update_snapshot {
+            true => Some(snapshot::Options {
+                headers: &self.snapshot_header,
+                ignore: &self.snapshot_ignore,
+                update: self.update_snapshot,
+            }),
+            false => None,
+        }
+    }
+
+    pub fn load_test(&self) -> Option<loadtest::Options> {
+        match (self.repeat, self.duration) {
+            (None | Some(1), None) => None,
+            (repeat, duration) => Some(loadtest::Options {
+                repeat: repeat.map(|n| n as usize),
+                concurrency: self.concurrency as usize,
+                duration,
+            }),
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn query(&self) -> Option<&Query> {
+        self.query.as_ref()
+    }
+
+    pub fn timings(&self) -> bool {
+        self.timings
+    }
+
+    /// Whether anything but the body of the response should be printed
+    pub fn decorations(&self) -> bool {
+        !(self.body || self.raw)
+    }
+
+    pub fn raw(&self) -> bool {
+        self.raw
+    }
+
+    pub fn theme(&self) -> Option<String> {
+        self.theme
+            .clone()
+            .or_else(|| std::env::var("FIRE_THEME").ok())
+            .or_else(|| self.config.theme.clone())
+    }
+
+    pub fn paging(&self) -> Paging {
+        match (self.pager, self.no_pager) {
+            (true, _) => Paging::Always,
+            (_, true) => Paging::Never,
+            (false, false) => match self.config.pager {
+                Some(Pager::Enabled(true)) => Paging::Always,
+                Some(Pager::Enabled(false)) => Paging::Never,
+                Some(Pager::Command(_)) | None => Paging::Auto,
+            },
+        }
+    }
+
+    pub fn save_history(&self) -> bool {
+        match (self.history, self.no_history) {
+            (true, _) => true,
+            (_, true) => false,
+            (false, false) => self.config.history.unwrap_or(false),
+        }
+    }
+
+    /// Command for the pager, from `$FIRE_PAGER` or the configuration
+    pub fn pager_command(&self) -> Option<String> {
+        match (std::env::var("FIRE_PAGER"), &self.config.pager) {
+            (Ok(command), _) => Some(command),
+            (Err(_), Some(Pager::Command(command))) => Some(command.clone()),
+            (Err(_), _) => None,
+        }
+    }
+
+    /// Selected environments, or the environments in the configuration if none are selected
+    pub fn environments(&self) -> &[String] {
+        match (self.env.is_empty(), &self.config.environments) {
+            (true, Some(environments)) => environments,
+            _ => &self.env,
+        }
+    }
+
+    pub fn export(&self) -> Option<Export> {
+        self.export
+    }
+
     pub fn env(&self) -> Result<Vec<Property>, ParsePropertyError> {
         let sys_envs: Vec<Property> = Self::read_sys_envs()?;
         let file_envs: Vec<Property> = self.read_file_envs()?;
@@ -186,14 +657,16 @@ impl Args {
 
     fn read_file_envs(&self) -> Result<Vec<Property>, ParsePropertyError> {
         let file_envs: Result<Vec<Vec<Property>>, ParsePropertyError> =
-            Self::find_env_files(&self.file, self.env.clone())
-                .into_iter()
-                .map(|file| prop::from_file(&file))
-                .collect();
+            self.env_files().into_iter().map(|file| prop::from_file(&file)).collect();
 
         file_envs.map(|vec| vec.into_iter().flatten().collect())
     }
 
+    /// Environments files for the request file and the active environments
+    pub fn env_files(&self) -> Vec<PathBuf> {
+        Self::find_env_files(self.file(), self.environments().to_vec())
+    }
+
     fn read_arg_vars(&self) -> Vec<Property> {
         self.arg_vars
             .clone()
@@ -211,32 +684,83 @@ impl Args {
         files.push(String::from(".env"));
         files.push(String::from(".sec"));
 
-        let end: PathBuf = request_file.canonicalize().unwrap().parent().unwrap().to_path_buf();
+        let dir: PathBuf = request_file.canonicalize().unwrap().parent().unwrap().to_path_buf();
+        find_files(&dir, &files)
+            .into_iter()
+            .inspect(|file| log::debug!("Found environments file {:?}", file))
+            .collect()
+    }
+}
+
+/// Find files with any of the given names in a directory, and in each of its parent directories up
+/// to the root of the git repository that the directory is in. Files in parent directories come
+/// before files in their subdirectories, and files in the same directory come in the order of
+/// `names`.
+pub fn find_files(dir: &Path, names: &[String]) -> Vec<PathBuf> {
+    let end: PathBuf = dir.to_path_buf();
+
+    let start: PathBuf = match giro::git_root(&end).expect("Resolve git root") {
+        Some(root) => root.parent().unwrap().to_path_buf(),
+        None => end.clone(),
+    };
+
+    log::info!("Start is {:?}", start);
+    log::info!("End is {:?}", end);
+    let mut files: Vec<PathBuf> = WalkDir::new(start)
+        .follow_links(false)
+        .contents_first(false)
+        .into_iter()
+        .filter_entry(|entry| end.starts_with(entry.path()) || entry.file_type().is_file())
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let ftype = entry.file_type();
+            if ftype.is_file() {
+                let name: String = entry.file_name().to_str().unwrap().to_string();
+                names.contains(&name)
+            } else {
+                false
+            }
+        })
+        .map(|e| e.into_path())
+        .collect();
+
+    // Entries in a directory are walked in the order that the file system lists them
+    files.sort_by_key(|file| {
+        let name: Option<&str> = file.file_name().and_then(|name| name.to_str());
+        (file.components().count(), names.iter().position(|n| Some(n.as_str()) == name))
+    });
+    files
+}
 
-        let start: PathBuf = match giro::git_root(&end).expect("Resolve git root") {
-            Some(root) => root.parent().unwrap().to_path_buf(),
-            None => end.clone(),
-        };
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use httpx::test_util::TempDir;
+
+    use super::find_files;
+
+    #[test]
+    fn test_find_files_in_parents_first() {
+        let dir = TempDir::new("fire-find");
+        let repo: PathBuf = dir.path().canonicalize().unwrap().join("repo");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::write(repo.join(".git").join("config"), "").unwrap();
+
+        // Files are created before subdirectories, which some file systems list first
+        let leaf: PathBuf = repo.join("a").join("b");
+        let names: Vec<String> = vec![String::from(".env"), String::from(".secrets")];
+        for (i, dir) in [&repo, &repo.join("a"), &leaf].into_iter().enumerate() {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(dir.join(".secrets"), "").unwrap();
+            std::fs::write(dir.join(".env"), "").unwrap();
+            std::fs::create_dir_all(dir.join(format!("sibling{i}"))).unwrap();
+        }
 
-        log::info!("Start is {:?}", start);
-        log::info!("End is {:?}", end);
-        WalkDir::new(start)
-            .follow_links(false)
-            .contents_first(false)
+        let expected: Vec<PathBuf> = [&repo, &repo.join("a"), &leaf]
             .into_iter()
-            .filter_entry(|entry| end.starts_with(entry.path()) || entry.file_type().is_file())
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| {
-                let ftype = entry.file_type();
-                if ftype.is_file() {
-                    let name: String = entry.file_name().to_str().unwrap().to_string();
-                    files.contains(&name)
-                } else {
-                    false
-                }
-            })
-            .inspect(|e| log::debug!("Found environments file {:?}", e))
-            .map(|e| e.into_path())
-            .collect()
+            .flat_map(|dir| names.iter().map(|name| dir.join(name)))
+            .collect();
+        assert_eq!(expected, find_files(&leaf, &names));
     }
 }
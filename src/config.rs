@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::args::find_files;
+use crate::error::FireError;
+
+/// Name of configuration files for a project, which apply to requests in the directory of the file
+/// and in any of its subdirectories
+const PROJECT_FILE: &str = ".fire.toml";
+
+/// Default values for command line options. Options given on the command line always take
+/// precedence over the configuration.
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub timeout: Option<usize>,
+    pub colors: Option<Colors>,
+    pub verbosity: Option<u8>,
+    pub headers: Option<bool>,
+    pub environments: Option<Vec<String>>,
+    pub theme: Option<String>,
+    pub pager: Option<Pager>,
+    /// Save executed requests to the history
+    pub history: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Colors {
+    Auto,
+    Always,
+    Never,
+}
+
+/// Either `true` to always use a pager and `false` to never use a pager, or the command for the
+/// pager
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Pager {
+    Enabled(bool),
+    Command(String),
+}
+
+impl Config {
+    /// Merge with another configuration, where values in the other configuration take precedence
+    fn merge(self, other: Config) -> Config {
+        Config {
+            timeout: other.timeout.or(self.timeout),
+            colors: other.colors.or(self.colors),
+            verbosity: other.verbosity.or(self.verbosity),
+            headers: other.headers.or(self.headers),
+            environments: other.environments.or(self.environments),
+            theme: other.theme.or(self.theme),
+            pager: other.pager.or(self.pager),
+            history: other.history.or(self.history),
+        }
+    }
+
+    fn read(path: &Path) -> Result<Config, FireError> {
+        let invalid = |e: String| FireError::Config(format!("{}: {e}", path.display()));
+        let content: String = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let config: Config = toml::from_str(&content).map_err(|e| invalid(e.message().into()))?;
+        match config.verbosity {
+            Some(verbosity) if verbosity > 5 => {
+                Err(invalid(format!("verbosity must be 0 - 5, was {verbosity}")))
+            }
+            _ => Ok(config),
+        }
+    }
+}
+
+/// Load the configuration for requests in a directory. The user configuration in
+/// `config.toml` in the configuration directory is merged with any `.fire.toml` files in the
+/// directory and its parent directories, up to the root of the git repository. A file closer to
+/// the directory takes precedence.
+pub fn load(dir: &Path) -> Result<Config, FireError> {
+    let user: Option<PathBuf> = self::dir().map(|dir| dir.join("config.toml"));
+    let project: Vec<PathBuf> = find_files(dir, &[String::from(PROJECT_FILE)]);
+
+    user.into_iter()
+        .filter(|path| path.is_file())
+        .chain(project)
+        .try_fold(Config::default(), |config, path| Ok(config.merge(Config::read(&path)?)))
+}
+
+/// Directory for configuration, which is `$XDG_CONFIG_HOME/fire`, or `~/.config/fire` if
+/// `XDG_CONFIG_HOME` is not set
+pub fn dir() -> Option<PathBuf> {
+    let config_home: PathBuf = match std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => dir,
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("fire"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Colors, Config, Pager};
+
+    #[test]
+    fn test_merge_configs() {
+        let user: Config = toml::from_str(
+            r#"
+            timeout = 10
+            colors = "never"
+            headers = true
+            environments = ["dev"]
+            pager = "bat --plain"
+            "#,
+        )
+        .unwrap();
+
+        let project: Config = toml::from_str(
+            r#"
+            environments = ["staging", "local"]
+            pager = false
+            "#,
+        )
+        .unwrap();
+
+        let config: Config = user.merge(project);
+        assert_eq!(Some(10), config.timeout);
+        assert_eq!(Some(Colors::Never), config.colors);
+        assert_eq!(Some(true), config.headers);
+        assert_eq!(Some(vec![String::from("staging"), String::from("local")]), config.environments);
+        assert_eq!(Some(Pager::Enabled(false)), config.pager);
+        assert_eq!(None, config.theme);
+
+        assert!(toml::from_str::<Config>("timout = 10").is_err());
+    }
+}
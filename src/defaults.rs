@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use http::{HeaderName, HeaderValue};
+use httpx::HttpRequest;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::args::find_files;
+use crate::error::FireError;
+use crate::request;
+
+/// Name of files with defaults for requests in the same directory and in its subdirectories
+const FILE: &str = "_defaults.yml";
+
+/// Values shared by all requests in a directory, from `_defaults.yml` files. Values set in a
+/// request always take precedence.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    /// Prepended to the URL of requests where the URL is only a path, such as `/users`
+    pub base_url: Option<String>,
+    pub headers: BTreeMap<String, String>,
+    /// Query parameters added to the URL of requests, unless already present
+    pub query: BTreeMap<String, String>,
+    /// Timeout in seconds
+    pub timeout: Option<usize>,
+}
+
+impl Defaults {
+    /// Merge with defaults from a directory closer to the request file, which take precedence.
+    /// Header names are case-insensitive, so they are lowercased before they are merged.
+    fn merge(mut self, closer: Defaults) -> Defaults {
+        let lowercase = |headers: BTreeMap<String, String>| {
+            headers.into_iter().map(|(key, value)| (key.to_lowercase(), value))
+        };
+        let mut headers: BTreeMap<String, String> = lowercase(self.headers).collect();
+        headers.extend(lowercase(closer.headers));
+        self.query.extend(closer.query);
+        Defaults {
+            base_url: closer.base_url.or(self.base_url),
+            headers,
+            query: self.query,
+            timeout: closer.timeout.or(self.timeout),
+        }
+    }
+
+    /// Apply defaults to a request, for anything that is not set in the request itself. When there
+    /// is a base URL, headers and query parameters are only added to requests to the base URL, so
+    /// that credentials are not sent to other hosts.
+    pub fn apply(&self, request: &mut HttpRequest) -> Result<(), FireError> {
+        if let Some(base_url) = &self.base_url {
+            let url: &str = request.raw_url();
+            if url.is_empty() || url.starts_with('/') || url.starts_with('?') {
+                let url: String = format!("{}{url}", base_url.trim_end_matches('/'));
+                request.set_url(url);
+            } else if !request.url().is_ok_and(|url| same_origin(base_url, &url)) {
+                log::debug!("Defaults are not applied to {url}, which is not on {base_url}");
+                return Ok(());
+            }
+        }
+
+        for (key, value) in &self.headers {
+            let invalid = || FireError::Config(format!("Invalid header in {FILE}: {key}"));
+            let key: HeaderName = HeaderName::from_str(key).map_err(|_| invalid())?;
+            let value: HeaderValue = HeaderValue::from_str(value).map_err(|_| invalid())?;
+            request.headers_mut().entry(key).or_insert(value);
+        }
+
+        request::add_query(request, &self.query)
+    }
+}
+
+/// Whether a URL has the same scheme, host and port as the base URL
+fn same_origin(base_url: &str, url: &Url) -> bool {
+    Url::parse(base_url).is_ok_and(|base_url| base_url.origin() == url.origin())
+}
+
+/// Load the defaults for a request file, from each `_defaults.yml` file in the directory of the
+/// request file and its parent directories, up to the root of the git repository. Defaults in a
+/// directory closer to the request file take precedence. Each file is rendered as a template with
+/// `render` before it is parsed.
+pub fn load(
+    request_file: &Path,
+    render: impl Fn(String) -> Result<String, FireError>,
+) -> Result<Defaults, FireError> {
+    let dir: PathBuf = match request_file.canonicalize() {
+        Ok(file) => file.parent().unwrap().to_path_buf(),
+        Err(_) => return Ok(Defaults::default()),
+    };
+
+    find_files(&dir, &[String::from(FILE)])
+        .into_iter()
+        .inspect(|file| log::debug!("Found defaults file {:?}", file))
+        .try_fold(Defaults::default(), |defaults, file| {
+            let invalid = |e: String| FireError::Config(format!("{}: {e}", file.display()));
+            let content: String =
+                std::fs::read_to_string(&file).map_err(|e| invalid(e.to_string()))?;
+            let content: String = render(content)?;
+            let closer: Defaults = match content.trim().is_empty() {
+                true => Defaults::default(),
+                false => serde_yaml::from_str(&content).map_err(|e| invalid(e.to_string()))?,
+            };
+            Ok(defaults.merge(closer))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    use httpx::test_util::TempDir;
+    use httpx::HttpRequest;
+
+    use super::{load, Defaults};
+
+    #[test]
+    fn test_apply_defaults() {
+        let parent: Defaults = serde_yaml::from_str(
+            "base_url: https://example.com/api/\nheaders:\n  authorization: Bearer a\n  accept: text/plain\nquery:\n  version: '1'\ntimeout: 10\n",
+        )
+        .unwrap();
+        let closer: Defaults = serde_yaml::from_str(
+            "base_url: https://billing.example.com\nheaders:\n  authorization: Bearer b\nquery:\n  page: '1'\n",
+        )
+        .unwrap();
+        let defaults: Defaults = parent.merge(closer);
+        assert_eq!(Some(10), defaults.timeout);
+
+        let input = "method: GET\nurl: /invoices?page=2\nheaders:\n  accept: application/json\n";
+        let mut request = HttpRequest::from_str(input).unwrap();
+        defaults.apply(&mut request).unwrap();
+
+        assert_eq!("https://billing.example.com/invoices?page=2&version=1", request.raw_url());
+        assert_eq!(Some("Bearer b"), request.header("authorization"));
+        assert_eq!(Some("application/json"), request.header("accept"));
+
+        let input = "method: GET\nurl: https://billing.example.com/status\n";
+        let mut request = HttpRequest::from_str(input).unwrap();
+        defaults.apply(&mut request).unwrap();
+        assert_eq!("https://billing.example.com/status?page=1&version=1", request.raw_url());
+        assert_eq!(Some("Bearer b"), request.header("authorization"));
+
+        // Headers and query parameters are not sent to other hosts
+        let input = "method: GET\nurl: https://other.example.com/status\n";
+        let mut request = HttpRequest::from_str(input).unwrap();
+        defaults.apply(&mut request).unwrap();
+        assert_eq!("https://other.example.com/status", request.raw_url());
+        assert_eq!(None, request.header("authorization"));
+    }
+
+    #[test]
+    fn test_load_closer_defaults_first() {
+        let dir = TempDir::new("fire-defaults");
+        let root: PathBuf = dir.join("a");
+        let leaf: PathBuf = root.join("b");
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join(".git").join("config"), "").unwrap();
+        std::fs::write(
+            root.join("_defaults.yml"),
+            "headers:\n  x-level: root\n  Authorization: Bearer root\n  accept: text/plain\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(&leaf).unwrap();
+        std::fs::write(
+            leaf.join("_defaults.yml"),
+            "headers:\n  X-Level: leaf\n  authorization: Bearer leaf\n",
+        )
+        .unwrap();
+        std::fs::write(leaf.join("req.yml"), "method: GET\nurl: https://example.com\n").unwrap();
+
+        let defaults: Defaults = load(&leaf.join("req.yml"), Ok).unwrap();
+        let headers: Vec<(&str, &str)> =
+            defaults.headers.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        assert_eq!(
+            vec![
+                ("accept", "text/plain"),
+                ("authorization", "Bearer leaf"),
+                ("x-level", "leaf")
+            ],
+            headers
+        );
+    }
+}
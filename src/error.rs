@@ -4,11 +4,14 @@ use std::fmt::Display;
 use std::path::PathBuf;
 use std::process::{self, ExitCode, Termination};
 
+use httpx::grpc::GrpcError;
 use url::Url;
 
 use crate::prop;
 use crate::prop::ParsePropertyError;
+use crate::template::MissingKeys;
 
+#[allow(dead_code)]
 pub trait Error: StdError + Termination {}
 
 pub enum FireError {
@@ -18,9 +21,15 @@ pub enum FireError {
     NoReadPermission(PathBuf),
     NotAFile(PathBuf),
     GenericIO(String),
-    TemplateRendering,
-    TemplateKey(String),
+    TemplateRendering(String),
+    TemplateKey(MissingKeys),
     Environment(ParsePropertyError),
+    Grpc(GrpcError),
+    Import(String),
+    Snapshot(PathBuf),
+    Query(String),
+    Config(String),
+    Extends(String),
     Other(String),
 }
 
@@ -39,14 +48,27 @@ impl Display for FireError {
             FireError::GenericIO(err) => format!("IO error: {err}"),
             FireError::NotAFile(path) => format!("{:?} exists but it is not a file", path.clone()),
             FireError::NoReadPermission(path) => format!("No permission to read file {:?}", path.clone()),
-            FireError::TemplateRendering => String::from("Unable to render request from template"),
-            FireError::TemplateKey(key) => format!("Unable to render request due to missing value for key {key}"),
+            FireError::TemplateRendering(err) => format!("Unable to render request from template: {err}"),
+            FireError::TemplateKey(missing) => missing_keys(missing),
             FireError::Environment(err) => match err {
                 prop::ParsePropertyError::Entry(entry) => format!("Invalid entry in environments file: {entry}"),
                 prop::ParsePropertyError::Key(key) => format!("Invalid key in environments file: {key}"),
                 prop::ParsePropertyError::Value(value) => format!("Invalid value in environments file: {value}"),
                 prop::ParsePropertyError::File(file) => format!("Invalid environments file: {file}"),
             },
+            FireError::Grpc(err) => match err {
+                GrpcError::Descriptor(msg) => format!("Unable to load protobuf descriptors: {msg}"),
+                GrpcError::UnknownMethod(method) => format!("No gRPC method {method} found in protobuf descriptors"),
+                GrpcError::Streaming(method) => format!("gRPC method {method} is a streaming method, only unary methods are supported"),
+                GrpcError::Message(msg) => format!("Invalid gRPC message: {msg}"),
+                GrpcError::Transport(err) => format!("gRPC transport error: {err:?}"),
+                GrpcError::Other(msg) => format!("gRPC error: {msg}"),
+            },
+            FireError::Import(err) => format!("Unable to import request: {err}"),
+            FireError::Snapshot(path) => format!("Response does not match snapshot {:?}", path),
+            FireError::Query(err) => format!("Unable to apply query: {err}"),
+            FireError::Config(err) => format!("Invalid configuration: {err}"),
+            FireError::Extends(err) => format!("Unable to extend request file: {err}"),
             FireError::Other(err) => format!("Error: {err}"),
         };
 
@@ -54,6 +76,34 @@ impl Display for FireError {
     }
 }
 
+fn missing_keys(missing: &MissingKeys) -> String {
+    let mut msg: String = match missing.keys.as_slice() {
+        [key] => format!("Unable to render request due to missing value for key {key}"),
+        keys => {
+            format!("Unable to render request due to missing values for keys {}", keys.join(", "))
+        }
+    };
+
+    match missing.environments.is_empty() {
+        true => msg.push_str("\nNo environments are active, select environments with --env"),
+        false => {
+            msg.push_str(&format!("\nActive environments: {}", missing.environments.join(", ")))
+        }
+    }
+
+    match missing.env_files.is_empty() {
+        true => msg.push_str("\nNo environments files were found"),
+        false => {
+            msg.push_str("\nEnvironments files searched:");
+            for file in &missing.env_files {
+                msg.push_str(&format!("\n  {}", file.display()));
+            }
+        }
+    }
+
+    msg
+}
+
 impl Termination for FireError {
     fn report(self) -> process::ExitCode {
         match self {
@@ -64,8 +114,14 @@ impl Termination for FireError {
             FireError::NotAFile(_) => ExitCode::from(7),
             FireError::GenericIO(_) => ExitCode::from(8),
             FireError::TemplateKey(_) => ExitCode::from(9),
-            FireError::TemplateRendering => ExitCode::from(10),
+            FireError::TemplateRendering(_) => ExitCode::from(10),
             FireError::Environment(_) => ExitCode::from(11),
+            FireError::Grpc(_) => ExitCode::from(12),
+            FireError::Import(_) => ExitCode::from(13),
+            FireError::Snapshot(_) => ExitCode::from(14),
+            FireError::Query(_) => ExitCode::from(15),
+            FireError::Config(_) => ExitCode::from(16),
+            FireError::Extends(_) => ExitCode::from(17),
             FireError::Other(_) => ExitCode::from(1),
         }
     }
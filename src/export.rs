@@ -0,0 +1,153 @@
+use clap::ValueEnum;
+use httpx::HttpRequest;
+
+use crate::error::FireError;
+
+/// Line continuation used between the arguments of an exported command
+const CONTINUATION: &str = " \\\n  ";
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Export {
+    Curl,
+    Httpie,
+    Wget,
+}
+
+/// Render a request as a shell command for another HTTP client, with every argument quoted so it
+/// can be pasted directly into a shell. Requests over a Unix domain socket can only be exported as
+/// curl, since neither httpie nor wget supports it.
+pub fn command(request: &HttpRequest, export: Export) -> Result<String, FireError> {
+    let url: String = request.url().unwrap().to_string();
+    let method: String = request.method().to_string();
+    let headers: Vec<String> = request
+        .headers()
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.to_str().unwrap_or_default()))
+        .map(|(key, value)| match export {
+            Export::Httpie => format!("{key}:{value}"),
+            _ => format!("{key}: {value}"),
+        })
+        .collect();
+
+    let body: Option<&str> = request.sent_body();
+
+    if request.unix_socket().is_some() && export != Export::Curl {
+        return Err(FireError::Other(String::from(
+            "Requests over Unix domain sockets can only be exported as curl",
+        )));
+    }
+
+    let mut args: Vec<String> = Vec::new();
+    match export {
+        Export::Curl => {
+            args.push(String::from("curl"));
+            args.push(format!("-X {method}"));
+            if let Some(socket) = request.unix_socket() {
+                args.push(format!("--unix-socket {}", quote(&socket.to_string_lossy())));
+            }
+            args.push(quote(&url));
+            args.extend(headers.iter().map(|header| format!("-H {}", quote(header))));
+            if let Some(body) = body {
+                args.push(format!("--data-raw {}", quote(body)));
+            }
+        }
+        Export::Httpie => {
+            args.push(String::from("http"));
+            if let Some(body) = body {
+                args.push(format!("--raw {}", quote(body)));
+            }
+            args.push(method);
+            args.push(quote(&url));
+            args.extend(headers.iter().map(|header| quote(header)));
+        }
+        Export::Wget => {
+            args.push(String::from("wget"));
+            args.push(format!("--method={method}"));
+            args.extend(headers.iter().map(|header| format!("--header={}", quote(header))));
+            if let Some(body) = body {
+                args.push(format!("--body-data={}", quote(body)));
+            }
+            args.push(String::from("--output-document=-"));
+            args.push(quote(&url));
+        }
+    }
+
+    Ok(args.join(CONTINUATION))
+}
+
+fn quote(arg: &str) -> String {
+    shell_words::quote(arg).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use httpx::HttpRequest;
+
+    use super::{command, Export};
+
+    #[test]
+    fn test_export_as_curl() {
+        let input = r###"
+            method: POST
+            url: https://api.example.com/users?active=true
+            headers:
+              content-type: application/json
+            body: '{"name": "fire''s"}'
+        "###;
+
+        let request = HttpRequest::from_str(input).unwrap();
+        let expected = r#"curl \
+  -X POST \
+  'https://api.example.com/users?active=true' \
+  -H 'content-type: application/json' \
+  --data-raw '{"name": "fire'\''s"}'"#;
+
+        assert_eq!(expected, command(&request, Export::Curl).unwrap());
+    }
+
+    #[test]
+    fn test_export_as_httpie_and_wget() {
+        let input = r###"
+            method: PUT
+            url: https://api.example.com/users/1
+            headers:
+              accept: application/json
+            body: '{"name": "fire"}'
+        "###;
+        let request = HttpRequest::from_str(input).unwrap();
+
+        let expected = r#"http \
+  --raw '{"name": "fire"}' \
+  PUT \
+  https://api.example.com/users/1 \
+  accept:application/json"#;
+        assert_eq!(expected, command(&request, Export::Httpie).unwrap());
+
+        let expected = r#"wget \
+  --method=PUT \
+  --header='accept: application/json' \
+  --body-data='{"name": "fire"}' \
+  --output-document=- \
+  https://api.example.com/users/1"#;
+        assert_eq!(expected, command(&request, Export::Wget).unwrap());
+    }
+
+    #[test]
+    fn test_export_without_body_or_socket() {
+        // A body is never sent with GET, so it is not exported either
+        let input = "method: GET\nurl: https://api.example.com/users\nbody: ignored\n";
+        let request = HttpRequest::from_str(input).unwrap();
+        assert_eq!(None, request.sent_body());
+        let expected =
+            "wget \\\n  --method=GET \\\n  --output-document=- \\\n  https://api.example.com/users";
+        assert_eq!(expected, command(&request, Export::Wget).unwrap());
+
+        let input = "method: GET\nurl: unix:///var/run/docker.sock:/v1.43/containers/json\n";
+        let request = HttpRequest::from_str(input).unwrap();
+        assert!(command(&request, Export::Curl).is_ok());
+        assert!(command(&request, Export::Httpie).is_err());
+        assert!(command(&request, Export::Wget).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::{Path, PathBuf};
+
+use serde_yaml::Value;
+
+use crate::error::FireError;
+
+/// Key for the path of the request file that a request file extends
+const EXTENDS: &str = "extends";
+
+/// Resolve `extends` in a rendered request file, where the request file is deep-merged on top of
+/// the request file that it extends. The path of the extended request file is relative to the
+/// request file, and it may in turn extend another request file. Each extended request file is
+/// rendered as a template with `render` before it is parsed.
+///
+/// Content without `extends` is returned as it is.
+pub fn resolve(
+    content: String,
+    file: &Path,
+    render: impl Fn(String) -> Result<String, FireError>,
+) -> Result<String, FireError> {
+    let yaml: Value = match serde_yaml::from_str(&content) {
+        Ok(yaml @ Value::Mapping(_)) if yaml.get(EXTENDS).is_some() => yaml,
+        _ => return Ok(content),
+    };
+
+    let file: PathBuf = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+    let merged: Value = extend(yaml, &mut vec![file], &render)?;
+    Ok(serde_yaml::to_string(&merged).unwrap())
+}
+
+/// Merge a request file with the request file it extends, if any, where `chain` is the request
+/// files that have been visited so far, with the request file of `yaml` last
+fn extend(
+    mut yaml: Value,
+    chain: &mut Vec<PathBuf>,
+    render: &impl Fn(String) -> Result<String, FireError>,
+) -> Result<Value, FireError> {
+    let parent: String = match yaml.as_mapping_mut().and_then(|map| map.remove(EXTENDS)) {
+        Some(Value::String(parent)) => parent,
+        Some(_) => return Err(FireError::Extends(String::from("Value of extends must be a path"))),
+        None => return Ok(yaml),
+    };
+
+    let dir: &Path = chain.last().and_then(|file| file.parent()).unwrap_or(Path::new("."));
+    let path: PathBuf = dir.join(&parent);
+    let path: PathBuf = path.canonicalize().map_err(|_| FireError::FileNotFound(path.clone()))?;
+
+    if chain.contains(&path) {
+        chain.push(path);
+        let cycle: Vec<String> = chain.iter().map(|file| file.display().to_string()).collect();
+        return Err(FireError::Extends(format!("Cyclic extends {}", cycle.join(" -> "))));
+    }
+
+    let content: String = std::fs::read_to_string(&path)
+        .map_err(|e| FireError::Extends(format!("{}: {e}", path.display())))?;
+    let parent: Value = serde_yaml::from_str(&render(content)?)
+        .map_err(|e| FireError::Extends(format!("{}: {e}", path.display())))?;
+
+    chain.push(path);
+    let parent: Value = extend(parent, chain, render)?;
+    chain.pop();
+
+    Ok(merge(parent, yaml))
+}
+
+/// Deep-merge two YAML documents, where mappings are merged key by key and any other value in
+/// `child` replaces the value in `parent`
+fn merge(parent: Value, child: Value) -> Value {
+    match (parent, child) {
+        (Value::Mapping(mut parent), Value::Mapping(child)) => {
+            for (key, value) in child {
+                match parent.get_mut(&key) {
+                    Some(existing) => *existing = merge(std::mem::take(existing), value),
+                    None => {
+                        parent.insert(key, value);
+                    }
+                }
+            }
+            Value::Mapping(parent)
+        }
+        (_, child) => child,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use httpx::test_util::TempDir;
+
+    use super::resolve;
+    use crate::error::FireError;
+
+    #[test]
+    fn test_resolve_extends() {
+        let dir = TempDir::new("fire-extends");
+        let dir: &Path = dir.path();
+        std::fs::create_dir_all(dir.join("base")).unwrap();
+        std::fs::write(
+            dir.join("base").join("create-user.yml"),
+            "method: POST\nurl: https://example.com/users\nheaders:\n  accept: application/json\n  authorization: Bearer {{TOKEN}}\nquery:\n  notify: 'true'\nbody: '{}'\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("admin.yml"),
+            "extends: ./base/create-user.yml\nheaders:\n  authorization: Bearer admin\nquery:\n  role: admin\nbody: '{\"name\":\"admin\"}'\n",
+        )
+        .unwrap();
+        let render = |content: String| Ok(content.replace("{{TOKEN}}", "token"));
+
+        let content: String = std::fs::read_to_string(dir.join("admin.yml")).unwrap();
+        let resolved: String = resolve(content, &dir.join("admin.yml"), render).unwrap();
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&resolved).unwrap();
+
+        assert_eq!(yaml["method"], "POST");
+        assert_eq!(yaml["headers"]["accept"], "application/json");
+        assert_eq!(yaml["headers"]["authorization"], "Bearer admin");
+        assert_eq!(yaml["query"]["notify"], "true");
+        assert_eq!(yaml["query"]["role"], "admin");
+        assert_eq!(yaml["body"], r#"{"name":"admin"}"#);
+        assert!(yaml.get("extends").is_none());
+
+        std::fs::write(dir.join("a.yml"), "extends: b.yml\n").unwrap();
+        std::fs::write(dir.join("b.yml"), "extends: a.yml\n").unwrap();
+        let content: String = std::fs::read_to_string(dir.join("a.yml")).unwrap();
+        let err = resolve(content, &dir.join("a.yml"), render).unwrap_err();
+        assert!(matches!(err, FireError::Extends(msg) if msg.starts_with("Cyclic extends")));
+    }
+}
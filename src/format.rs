@@ -1,7 +1,8 @@
+use lazy_static::lazy_static;
 use syntect::{
     easy::HighlightLines,
-    highlighting::{Style, Theme, ThemeSet},
-    parsing::SyntaxSet,
+    highlighting::{Style, Theme},
+    parsing::{SyntaxDefinition, SyntaxSet},
     util::{as_24_bit_terminal_escaped, LinesWithEndings},
 };
 
@@ -10,52 +11,126 @@ pub trait ContentFormatter {
     fn format(&self, content: String) -> Result<String, String>;
 }
 
-pub fn formatters(use_colors: bool) -> Vec<Box<dyn ContentFormatter>> {
-    let mut formatters: Vec<Box<dyn ContentFormatter>> = Vec::with_capacity(4);
+lazy_static! {
+    /// Syntaxes bundled with syntect, and TOML which is not
+    static ref SYNTAX_SET: SyntaxSet = {
+        let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+        let toml = include_str!("../resources/toml.sublime-syntax");
+        builder.add(SyntaxDefinition::load_from_str(toml, true, None).unwrap());
+        builder.build()
+    };
+}
+
+/// Formatters for pretty printing bodies, which are also highlighted with the theme, if any
+pub fn formatters(theme: Option<Theme>) -> Vec<Box<dyn ContentFormatter>> {
+    let mut formatters: Vec<Box<dyn ContentFormatter>> = Vec::with_capacity(10);
     formatters.push(Box::new(JsonPretty::new()));
+    formatters.push(Box::new(XmlPretty::new()));
+    formatters.push(Box::new(YamlPretty::new()));
+    formatters.push(Box::new(TomlPretty::new()));
+    formatters.push(Box::new(FormPretty::new()));
 
-    if use_colors {
-        let theme_set = ThemeSet::load_defaults();
-        let theme: Theme = theme_set.themes["base16-mocha.dark"].clone();
+    if let Some(theme) = theme {
         formatters.push(Box::new(JsonSyntax::new(theme.clone())));
-        formatters.push(Box::new(XmlSyntax::new(theme)));
+        formatters.push(Box::new(XmlSyntax::new(theme.clone())));
+        formatters.push(Box::new(YamlSyntax::new(theme.clone())));
+        formatters.push(Box::new(TomlSyntax::new(theme.clone())));
+        formatters.push(Box::new(FormSyntax::new(theme)));
     }
 
     formatters
 }
 
+/// Format content with each formatter that accepts its content type. If a formatter fails, such
+/// as when a body is not valid for its content type, the content is left as it was.
+pub fn format(
+    formatters: &[Box<dyn ContentFormatter>],
+    content_type: Option<&str>,
+    content: &str,
+) -> String {
+    formatters.iter().filter(|fmt| fmt.accept(content_type)).fold(
+        content.to_string(),
+        |content, fmt| match fmt.format(content.clone()) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                log::debug!("{e}");
+                content
+            }
+        },
+    )
+}
+
+/// The essence of a content type, such as `application/json` for `application/json; charset=utf-8`
+fn mime(content_type: Option<&str>) -> Option<String> {
+    content_type.map(|ct| ct.split(';').next().unwrap_or_default().trim().to_lowercase())
+}
+
+fn is_json(content_type: Option<&str>) -> bool {
+    match mime(content_type) {
+        Some(mime) => mime == "application/json" || mime.ends_with("+json"),
+        None => false,
+    }
+}
+
+fn is_xml(content_type: Option<&str>) -> bool {
+    match mime(content_type) {
+        Some(mime) => {
+            matches!(mime.as_str(), "text/html" | "text/xml" | "application/xml")
+                || mime.ends_with("+xml")
+        }
+        None => false,
+    }
+}
+
+fn is_yaml(content_type: Option<&str>) -> bool {
+    match mime(content_type) {
+        Some(mime) => {
+            matches!(
+                mime.as_str(),
+                "application/yaml" | "application/x-yaml" | "text/yaml" | "text/x-yaml"
+            ) || mime.ends_with("+yaml")
+        }
+        None => false,
+    }
+}
+
+fn is_toml(content_type: Option<&str>) -> bool {
+    matches!(mime(content_type).as_deref(), Some("application/toml" | "text/x-toml"))
+}
+
+fn is_form(content_type: Option<&str>) -> bool {
+    matches!(mime(content_type).as_deref(), Some("application/x-www-form-urlencoded"))
+}
+
+fn highlight(extension: &str, theme: &Theme, content: &str) -> String {
+    let syntax = SYNTAX_SET.find_syntax_by_extension(extension).unwrap();
+    let mut high = HighlightLines::new(syntax, theme);
+    let mut out: Vec<String> = Vec::with_capacity(512);
+    for line in LinesWithEndings::from(content) {
+        let ranges: Vec<(Style, &str)> = high.highlight_line(line, &SYNTAX_SET).unwrap();
+        let escaped = as_24_bit_terminal_escaped(&ranges[..], false);
+        out.push(escaped);
+    }
+    out.as_slice().join("")
+}
+
 pub struct JsonSyntax {
-    syntax_set: SyntaxSet,
     theme: Theme,
 }
 
 impl JsonSyntax {
     pub fn new(theme: Theme) -> JsonSyntax {
-        JsonSyntax {
-            syntax_set: SyntaxSet::load_defaults_newlines(),
-            theme,
-        }
+        JsonSyntax { theme }
     }
 }
 
 impl ContentFormatter for JsonSyntax {
     fn accept(&self, content_type: Option<&str>) -> bool {
-        match content_type {
-            Some(ct) => ct.starts_with("application/json"),
-            None => false,
-        }
+        is_json(content_type)
     }
 
     fn format(&self, content: String) -> Result<String, String> {
-        let syntax = self.syntax_set.find_syntax_by_extension("json").unwrap();
-        let mut high = HighlightLines::new(syntax, &self.theme);
-        let mut out: Vec<String> = Vec::with_capacity(512);
-        for line in LinesWithEndings::from(&content) {
-            let ranges: Vec<(Style, &str)> = high.highlight_line(line, &self.syntax_set).unwrap();
-            let escaped = as_24_bit_terminal_escaped(&ranges[..], false);
-            out.push(escaped);
-        }
-        Ok(out.as_slice().join(""))
+        Ok(highlight("json", &self.theme, &content))
     }
 }
 
@@ -69,10 +144,7 @@ impl JsonPretty {
 
 impl ContentFormatter for JsonPretty {
     fn accept(&self, content_type: Option<&str>) -> bool {
-        match content_type {
-            Some(ct) => ct.starts_with("application/json"),
-            None => false,
-        }
+        is_json(content_type)
     }
 
     fn format(&self, content: String) -> Result<String, String> {
@@ -83,36 +155,376 @@ impl ContentFormatter for JsonPretty {
 }
 
 pub struct XmlSyntax {
-    syntax_set: SyntaxSet,
     theme: Theme,
 }
 
 impl XmlSyntax {
     pub fn new(theme: Theme) -> XmlSyntax {
-        XmlSyntax {
-            syntax_set: SyntaxSet::load_defaults_newlines(),
-            theme,
-        }
+        XmlSyntax { theme }
     }
 }
 
 impl ContentFormatter for XmlSyntax {
     fn accept(&self, content_type: Option<&str>) -> bool {
-        match content_type {
-            Some(ct) => ct.starts_with("text/html") || ct.starts_with("text/xml"),
-            None => false,
-        }
+        is_xml(content_type)
     }
 
     fn format(&self, content: String) -> Result<String, String> {
-        let syntax = self.syntax_set.find_syntax_by_extension("xml").unwrap();
-        let mut high = HighlightLines::new(syntax, &self.theme);
-        let mut out: Vec<String> = Vec::with_capacity(512);
-        for line in LinesWithEndings::from(&content) {
-            let ranges: Vec<(Style, &str)> = high.highlight_line(line, &self.syntax_set).unwrap();
-            let escaped = as_24_bit_terminal_escaped(&ranges[..], false);
-            out.push(escaped);
+        Ok(highlight("xml", &self.theme, &content))
+    }
+}
+
+/// Re-indents XML and HTML, with one element per line. Elements which only contain text are kept
+/// on a single line, and the content of elements such as `<script>` and `<pre>` is left as it
+/// is. Since HTML is not always well-formed, no validation is done.
+pub struct XmlPretty;
+
+impl XmlPretty {
+    pub fn new() -> XmlPretty {
+        XmlPretty
+    }
+}
+
+impl ContentFormatter for XmlPretty {
+    fn accept(&self, content_type: Option<&str>) -> bool {
+        is_xml(content_type)
+    }
+
+    fn format(&self, content: String) -> Result<String, String> {
+        Ok(indent_xml(&content))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Node<'a> {
+    Open(&'a str),
+    Close(&'a str),
+    /// Self-closing and void elements, comments, declarations and processing instructions
+    Single(&'a str),
+    Text(&'a str),
+}
+
+/// Elements in HTML which never have any content, and therefore no closing tag
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements whose content is kept as it is
+const RAW_ELEMENTS: [&str; 4] = ["script", "style", "pre", "textarea"];
+
+fn xml_nodes(content: &str) -> Vec<Node<'_>> {
+    let mut nodes: Vec<Node> = Vec::new();
+    let mut rest: &str = content;
+
+    while !rest.is_empty() {
+        if !rest.starts_with('<') {
+            let end: usize = rest.find('<').unwrap_or(rest.len());
+            let text: &str = rest[..end].trim();
+            if !text.is_empty() {
+                nodes.push(Node::Text(text));
+            }
+            rest = &rest[end..];
+            continue;
+        }
+
+        let terminator: &str = if rest.starts_with("<!--") {
+            "-->"
+        } else if rest.starts_with("<![CDATA[") {
+            "]]>"
+        } else {
+            ">"
+        };
+        let end: usize = match terminator {
+            ">" => tag_end(rest),
+            _ => rest.find(terminator).map(|i| i + terminator.len()),
         }
-        Ok(out.as_slice().join(""))
+        .unwrap_or(rest.len());
+        let tag: &str = &rest[..end];
+        rest = &rest[end..];
+
+        let name: &str = tag
+            .trim_start_matches(['<', '/'])
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or_default();
+
+        if tag.starts_with("</") {
+            nodes.push(Node::Close(tag));
+        } else if tag.starts_with("<!")
+            || tag.starts_with("<?")
+            || tag.ends_with("/>")
+            || VOID_ELEMENTS.contains(&name.to_lowercase().as_str())
+        {
+            nodes.push(Node::Single(tag));
+        } else {
+            nodes.push(Node::Open(tag));
+            if RAW_ELEMENTS.contains(&name.to_lowercase().as_str()) {
+                let close: String = format!("</{name}");
+                let end: usize = rest.find(&close).unwrap_or(rest.len());
+                let raw: &str = rest[..end].trim_matches('\n');
+                if !raw.trim().is_empty() {
+                    nodes.push(Node::Text(raw));
+                }
+                rest = &rest[end..];
+            }
+        }
+    }
+
+    nodes
+}
+
+/// End of a tag, where a `>` inside of a quoted attribute value does not end the tag
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn indent_xml(content: &str) -> String {
+    let nodes: Vec<Node> = xml_nodes(content);
+    let mut out: String = String::with_capacity(content.len() * 2);
+    let mut depth: usize = 0;
+    let mut i: usize = 0;
+
+    while i < nodes.len() {
+        let indent: String = "  ".repeat(depth);
+        match (&nodes[i], nodes.get(i + 1), nodes.get(i + 2)) {
+            (Node::Open(open), Some(Node::Text(text)), Some(Node::Close(close)))
+                if !text.contains('\n') =>
+            {
+                out.push_str(&format!("{indent}{open}{text}{close}\n"));
+                i += 3;
+            }
+            (Node::Open(open), Some(Node::Close(close)), _) => {
+                out.push_str(&format!("{indent}{open}{close}\n"));
+                i += 2;
+            }
+            (Node::Open(open), _, _) => {
+                out.push_str(&format!("{indent}{open}\n"));
+                depth += 1;
+                i += 1;
+            }
+            (Node::Close(close), _, _) => {
+                depth = depth.saturating_sub(1);
+                out.push_str(&format!("{}{close}\n", "  ".repeat(depth)));
+                i += 1;
+            }
+            (Node::Single(node), _, _) | (Node::Text(node), _, _) => {
+                out.push_str(&format!("{indent}{node}\n"));
+                i += 1;
+            }
+        }
+    }
+
+    out
+}
+
+pub struct YamlSyntax {
+    theme: Theme,
+}
+
+impl YamlSyntax {
+    pub fn new(theme: Theme) -> YamlSyntax {
+        YamlSyntax { theme }
+    }
+}
+
+impl ContentFormatter for YamlSyntax {
+    fn accept(&self, content_type: Option<&str>) -> bool {
+        is_yaml(content_type)
+    }
+
+    fn format(&self, content: String) -> Result<String, String> {
+        Ok(highlight("yaml", &self.theme, &content))
+    }
+}
+
+pub struct YamlPretty;
+
+impl YamlPretty {
+    pub fn new() -> YamlPretty {
+        YamlPretty
+    }
+}
+
+impl ContentFormatter for YamlPretty {
+    fn accept(&self, content_type: Option<&str>) -> bool {
+        is_yaml(content_type)
+    }
+
+    fn format(&self, content: String) -> Result<String, String> {
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&content)
+            .map_err(|e| format!("Unable to parse body as YAML: {:?}", e))?;
+        serde_yaml::to_string(&yaml).map_err(|e| format!("Unable to format YAML: {:?}", e))
+    }
+}
+
+pub struct TomlSyntax {
+    theme: Theme,
+}
+
+impl TomlSyntax {
+    pub fn new(theme: Theme) -> TomlSyntax {
+        TomlSyntax { theme }
+    }
+}
+
+impl ContentFormatter for TomlSyntax {
+    fn accept(&self, content_type: Option<&str>) -> bool {
+        is_toml(content_type)
+    }
+
+    fn format(&self, content: String) -> Result<String, String> {
+        Ok(highlight("toml", &self.theme, &content))
+    }
+}
+
+pub struct TomlPretty;
+
+impl TomlPretty {
+    pub fn new() -> TomlPretty {
+        TomlPretty
+    }
+}
+
+impl ContentFormatter for TomlPretty {
+    fn accept(&self, content_type: Option<&str>) -> bool {
+        is_toml(content_type)
+    }
+
+    fn format(&self, content: String) -> Result<String, String> {
+        let toml: toml::Value = toml::from_str(&content)
+            .map_err(|e| format!("Unable to parse body as TOML: {:?}", e))?;
+        toml::to_string_pretty(&toml).map_err(|e| format!("Unable to format TOML: {:?}", e))
+    }
+}
+
+/// Highlights the fields of a form as printed by [FormPretty], which have the same `key = value`
+/// layout as Java properties files. There is no syntax for URL-encoded forms as such.
+pub struct FormSyntax {
+    theme: Theme,
+}
+
+impl FormSyntax {
+    pub fn new(theme: Theme) -> FormSyntax {
+        FormSyntax { theme }
+    }
+}
+
+impl ContentFormatter for FormSyntax {
+    fn accept(&self, content_type: Option<&str>) -> bool {
+        is_form(content_type)
+    }
+
+    fn format(&self, content: String) -> Result<String, String> {
+        Ok(highlight("properties", &self.theme, &content))
+    }
+}
+
+/// Prints each field of a form on a line of its own, with keys and values decoded
+pub struct FormPretty;
+
+impl FormPretty {
+    pub fn new() -> FormPretty {
+        FormPretty
+    }
+}
+
+impl ContentFormatter for FormPretty {
+    fn accept(&self, content_type: Option<&str>) -> bool {
+        is_form(content_type)
+    }
+
+    fn format(&self, content: String) -> Result<String, String> {
+        let fields: Vec<String> = url::form_urlencoded::parse(content.trim().as_bytes())
+            .map(|(key, value)| format!("{key} = {value}"))
+            .collect();
+        Ok(fields.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format, formatters, xml_nodes, ContentFormatter, Node};
+    use crate::theme;
+
+    #[test]
+    fn test_pretty_print_bodies() {
+        let formatters = formatters(None);
+
+        let html = r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>Fire</title></head><body><p class="a>b">Hello<br/>world</p><script>if (a < b) {}</script><div></div></body></html>"#;
+        let expected = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <title>Fire</title>
+  </head>
+  <body>
+    <p class="a>b">
+      Hello
+      <br/>
+      world
+    </p>
+    <script>if (a < b) {}</script>
+    <div></div>
+  </body>
+</html>
+"#;
+        assert_eq!(expected, format(&formatters, Some("text/html; charset=utf-8"), html));
+
+        let yaml = "{name: fire, tags: [a, b]}";
+        assert_eq!(
+            "name: fire\ntags:\n- a\n- b\n",
+            format(&formatters, Some("application/yaml"), yaml)
+        );
+
+        let toml = "title = 'fire'\n[owner]\nname = 'x'";
+        assert_eq!(
+            "title = \"fire\"\n\n[owner]\nname = \"x\"\n",
+            format(&formatters, Some("application/toml"), toml)
+        );
+
+        let form = "name=fire+cli&tag=a%26b";
+        assert_eq!(
+            "name = fire cli\ntag = a&b",
+            format(&formatters, Some("application/x-www-form-urlencoded"), form)
+        );
+
+        let highlighted: Vec<Box<dyn ContentFormatter>> =
+            super::formatters(Some(theme::load_from(None, None).unwrap()));
+        let form: String = format(&highlighted, Some("application/x-www-form-urlencoded"), form);
+        assert!(form.contains("\x1b[") && form.contains("fire cli"));
+
+        let json = r#"{"title":"Not found"}"#;
+        let expected = "{\n  \"title\": \"Not found\"\n}";
+        assert_eq!(expected, format(&formatters, Some("application/problem+json"), json));
+        assert_eq!(expected, format(&formatters, Some("Application/JSON; charset=utf-8"), json));
+        assert_eq!(json, format(&formatters, Some("application/jsonl"), json));
+
+        let invalid = "{not: json";
+        assert_eq!(invalid, format(&formatters, Some("application/json"), invalid));
+    }
+
+    #[test]
+    fn test_xml_nodes() {
+        let xml = r#"<?xml version="1.0"?><!-- <a> is not a tag --><a href="x>y" b='>'><![CDATA[1 < 2 > 0]]><c/><d x="/>" /></a>"#;
+        let expected = vec![
+            Node::Single(r#"<?xml version="1.0"?>"#),
+            Node::Single("<!-- <a> is not a tag -->"),
+            Node::Open(r#"<a href="x>y" b='>'>"#),
+            Node::Single("<![CDATA[1 < 2 > 0]]>"),
+            Node::Single("<c/>"),
+            Node::Single(r#"<d x="/>" />"#),
+            Node::Close("</a>"),
+        ];
+        assert_eq!(expected, xml_nodes(xml));
     }
 }
@@ -0,0 +1,300 @@
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use http::HeaderMap;
+use httpx::HttpResponse;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::error::FireError;
+
+const HAR_VERSION: &str = "1.2";
+
+/// A HTTP Archive, as specified by <http://www.softwareishard.com/blog/har-12-spec/>. Only the
+/// parts that are needed to import and export requests are modeled.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Har {
+    pub log: Log,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Log {
+    pub version: String,
+    pub creator: Creator,
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Creator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub started_date_time: String,
+    pub time: f64,
+    pub request: Request,
+    pub response: Response,
+    #[serde(default)]
+    pub cache: serde_json::Value,
+    pub timings: Timings,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub http_version: String,
+    #[serde(default)]
+    pub headers: Vec<NameValue>,
+    #[serde(default)]
+    pub query_string: Vec<NameValue>,
+    #[serde(default)]
+    pub cookies: Vec<NameValue>,
+    #[serde(default)]
+    pub headers_size: i64,
+    #[serde(default)]
+    pub body_size: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<PostData>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostData {
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub params: Vec<NameValue>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+    pub status: u16,
+    #[serde(default)]
+    pub status_text: String,
+    #[serde(default)]
+    pub http_version: String,
+    #[serde(default)]
+    pub headers: Vec<NameValue>,
+    #[serde(default)]
+    pub cookies: Vec<NameValue>,
+    pub content: Content,
+    #[serde(default)]
+    pub redirect_url: String,
+    #[serde(default)]
+    pub headers_size: i64,
+    #[serde(default)]
+    pub body_size: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Content {
+    pub size: i64,
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// Timings in milliseconds, where `-1` means that the timing does not apply to the request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Timings {
+    #[serde(default = "not_applicable")]
+    pub blocked: f64,
+    #[serde(default = "not_applicable")]
+    pub dns: f64,
+    #[serde(default = "not_applicable")]
+    pub connect: f64,
+    #[serde(default = "not_applicable")]
+    pub ssl: f64,
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+}
+
+fn not_applicable() -> f64 {
+    -1.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameValue {
+    pub name: String,
+    pub value: String,
+}
+
+impl Har {
+    pub fn from_file(path: &Path) -> Result<Har, FireError> {
+        let content: String = std::fs::read_to_string(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => FireError::FileNotFound(path.to_path_buf()),
+            _ => FireError::GenericIO(e.to_string()),
+        })?;
+        serde_json::from_str(&content)
+            .map_err(|e| FireError::Import(format!("Invalid HAR file {:?}: {e}", path)))
+    }
+}
+
+/// An executed request and its response, which can be added to a HAR log
+pub struct Exchange<'a> {
+    pub method: &'a str,
+    pub url: &'a Url,
+    pub headers: &'a HeaderMap,
+    pub body: Option<&'a str>,
+    pub response: &'a HttpResponse,
+    pub started: DateTime<Utc>,
+    pub duration: Duration,
+}
+
+impl From<&Exchange<'_>> for Entry {
+    fn from(exchange: &Exchange) -> Self {
+        let request_headers: Vec<NameValue> = name_values(exchange.headers);
+        let content_type: String = header(exchange.headers, "content-type");
+        let post_data: Option<PostData> = exchange.body.map(|body| PostData {
+            mime_type: content_type,
+            text: Some(body.to_string()),
+            params: Vec::new(),
+        });
+
+        let query_string: Vec<NameValue> = exchange
+            .url
+            .query_pairs()
+            .map(|(name, value)| NameValue {
+                name: name.to_string(),
+                value: value.to_string(),
+            })
+            .collect();
+
+        let response: &HttpResponse = exchange.response;
+        let status_text: &str = http::StatusCode::from_u16(response.status())
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or_default();
+
+        let millis: f64 = exchange.duration.as_secs_f64() * 1000.0;
+
+        Entry {
+            started_date_time: exchange.started.to_rfc3339(),
+            time: millis,
+            request: Request {
+                method: exchange.method.to_string(),
+                url: exchange.url.to_string(),
+                http_version: response.version().to_string(),
+                headers: request_headers,
+                query_string,
+                cookies: Vec::new(),
+                headers_size: -1,
+                body_size: exchange.body.map(|body| body.len() as i64).unwrap_or(0),
+                post_data,
+            },
+            response: Response {
+                status: response.status(),
+                status_text: status_text.to_string(),
+                http_version: response.version().to_string(),
+                headers: name_values(response.headers()),
+                cookies: Vec::new(),
+                content: Content {
+                    size: response.body_len() as i64,
+                    mime_type: header(response.headers(), "content-type"),
+                    text: Some(response.body().to_string()),
+                },
+                redirect_url: header(response.headers(), "location"),
+                headers_size: -1,
+                body_size: response.body_len() as i64,
+            },
+            cache: serde_json::Value::Object(Default::default()),
+            timings: match response.timings() {
+                Some(timings) => Timings::from(timings),
+                None => Timings {
+                    blocked: -1.0,
+                    dns: -1.0,
+                    connect: -1.0,
+                    ssl: -1.0,
+                    send: 0.0,
+                    wait: millis,
+                    receive: 0.0,
+                },
+            },
+        }
+    }
+}
+
+impl From<&httpx::Timings> for Timings {
+    fn from(timings: &httpx::Timings) -> Self {
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let ssl: Option<f64> = timings.tls.map(millis);
+
+        // In HAR, the time of the TLS handshake is also included in the time to connect
+        Timings {
+            blocked: -1.0,
+            dns: timings.dns.map(millis).unwrap_or(-1.0),
+            connect: timings
+                .connect
+                .map(|connect| millis(connect) + ssl.unwrap_or_default())
+                .unwrap_or(-1.0),
+            ssl: ssl.unwrap_or(-1.0),
+            send: 0.0,
+            wait: millis(timings.first_byte),
+            receive: millis(timings.transfer),
+        }
+    }
+}
+
+fn name_values(headers: &HeaderMap) -> Vec<NameValue> {
+    headers
+        .iter()
+        .map(|(key, value)| NameValue {
+            name: key.to_string(),
+            value: value.to_str().unwrap_or_default().to_string(),
+        })
+        .collect()
+}
+
+fn header(headers: &HeaderMap, key: &str) -> String {
+    headers
+        .get(key)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Append an exchange to the HAR log at `path`, which is created if it does not exist. Existing
+/// logs are modified as generic JSON, so that any properties in the log which are not modeled by
+/// [Har] are kept as they are.
+pub fn append(path: &Path, exchange: &Exchange) -> Result<(), FireError> {
+    let io_err = |e: std::io::Error| FireError::GenericIO(format!("{}: {e}", path.display()));
+    let entry: serde_json::Value = serde_json::to_value(Entry::from(exchange)).unwrap();
+
+    let mut har: serde_json::Value = if path.exists() {
+        let content: String = std::fs::read_to_string(path).map_err(io_err)?;
+        serde_json::from_str(&content)
+            .map_err(|e| FireError::Other(format!("Invalid HAR file {:?}: {e}", path)))?
+    } else {
+        let log = Log {
+            version: String::from(HAR_VERSION),
+            creator: Creator {
+                name: String::from("fire"),
+                version: String::from(clap::crate_version!()),
+            },
+            entries: Vec::new(),
+        };
+        serde_json::to_value(Har { log }).unwrap()
+    };
+
+    match har.pointer_mut("/log/entries").and_then(|entries| entries.as_array_mut()) {
+        Some(entries) => entries.push(entry),
+        None => return Err(FireError::Other(format!("Invalid HAR file {:?}: no log", path))),
+    }
+
+    let content: String = serde_json::to_string_pretty(&har).unwrap();
+    std::fs::write(path, content).map_err(io_err)
+}
@@ -0,0 +1,344 @@
+use std::fmt::Write;
+
+use base64::Engine;
+use chrono::{Local, Utc};
+use handlebars::{
+    handlebars_helper, Context, Handlebars, Helper, HelperResult, JsonRender, Output,
+    RenderContext, RenderError,
+};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::Value;
+
+/// Names of all built-in helpers, which are never treated as template keys
+pub const NAMES: [&str; 15] = [
+    "default",
+    "uuid",
+    "now",
+    "unix_ms",
+    "random_int",
+    "random_string",
+    "base64",
+    "base64url",
+    "urlencode",
+    "json",
+    "sha256",
+    "hmac",
+    "upper",
+    "lower",
+    "file",
+];
+
+/// Characters that are percent-encoded by `urlencode`, which is everything except the unreserved
+/// characters of RFC 3986
+pub const URL_ENCODE: &AsciiSet =
+    &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// Longest string that `random_string` generates, so that a mistyped length does not exhaust memory
+const MAX_STRING_LENGTH: u64 = 4096;
+
+const ALPHANUMERIC: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Register all built-in helpers
+pub fn register(reg: &mut Handlebars) {
+    reg.register_helper("default", Box::new(default));
+    reg.register_helper("uuid", Box::new(uuid));
+    reg.register_helper("now", Box::new(now));
+    reg.register_helper("unix_ms", Box::new(unix_ms));
+    reg.register_helper("random_int", Box::new(random_int));
+    reg.register_helper("random_string", Box::new(random_string));
+    reg.register_helper("base64", Box::new(base64_standard));
+    reg.register_helper("base64url", Box::new(base64_url));
+    reg.register_helper("urlencode", Box::new(urlencode));
+    reg.register_helper("json", Box::new(json));
+    reg.register_helper("sha256", Box::new(sha256));
+    reg.register_helper("hmac", Box::new(hmac));
+    reg.register_helper("upper", Box::new(upper));
+    reg.register_helper("lower", Box::new(lower));
+    reg.register_helper("file", Box::new(file));
+}
+
+handlebars_helper!(base64_standard: |s: str| base64::engine::general_purpose::STANDARD.encode(s));
+handlebars_helper!(base64_url: |s: str| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(s));
+handlebars_helper!(sha256: |s: str| hex(ring::digest::digest(&ring::digest::SHA256, s.as_bytes()).as_ref()));
+handlebars_helper!(hmac: |key: str, message: str| {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key.as_bytes());
+    hex(ring::hmac::sign(&key, message.as_bytes()).as_ref())
+});
+handlebars_helper!(upper: |s: str| s.to_uppercase());
+handlebars_helper!(lower: |s: str| s.to_lowercase());
+
+/// A value percent-encoded for use in a URL, such as `{{urlencode SEARCH}}`. The value is written
+/// as it is encoded here, regardless of where it is in a template.
+fn urlencode(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value: String = param(h, r, 0)?.map(|value| value.render()).unwrap_or_default();
+    out.write(&percent_encoding::utf8_percent_encode(&value, URL_ENCODE).to_string())?;
+    Ok(())
+}
+
+/// A value as JSON, such as `{{json NAME}}` which is a quoted and escaped string. The value is
+/// written as it is encoded here, regardless of where it is in a template.
+fn json(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value: &Value = param(h, r, 0)?.unwrap_or(&Value::Null);
+    out.write(&value.to_string())?;
+    Ok(())
+}
+
+/// Value of a variable, or a default value if the variable is missing or empty, such as
+/// `{{default PAGE_SIZE "20"}}`
+fn default(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    rc: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value: Option<&str> = h
+        .param(0)
+        .filter(|value| !value.is_value_missing())
+        .and_then(|value| value.value().as_str())
+        .filter(|value| !value.is_empty());
+    match value {
+        Some(value) => write(r, rc, out, value)?,
+        None => {
+            let default: &Value =
+                param(h, r, 1)?.ok_or_else(|| invalid("default", "a default value is required"))?;
+            write(r, rc, out, &default.render())?
+        }
+    }
+    Ok(())
+}
+
+/// A random UUID (version 4), such as `{{uuid}}`
+fn uuid(
+    _: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let mut bytes: [u8; 16] = random()?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = hex(&bytes);
+    let uuid: String = format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    );
+    out.write(&uuid)?;
+    Ok(())
+}
+
+/// The current local time, formatted in RFC 3339 unless a format is given, such as
+/// `{{now "%Y-%m-%d"}}`
+fn now(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    rc: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let format: &str = match param(h, r, 0)? {
+        Some(format) => format.as_str().ok_or_else(|| invalid("now", "format must be a string"))?,
+        None => "%+",
+    };
+    let mut now = String::new();
+    write!(now, "{}", Local::now().format(format))
+        .map_err(|_| invalid("now", &format!("invalid format '{format}'")))?;
+    write(r, rc, out, &now)?;
+    Ok(())
+}
+
+/// Milliseconds since the Unix epoch, such as `{{unix_ms}}`
+fn unix_ms(
+    _: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    out.write(&Utc::now().timestamp_millis().to_string())?;
+    Ok(())
+}
+
+/// A random integer from `min` to `max` (inclusive), such as `{{random_int 1 6}}`, where `min` is
+/// 0 and `max` is 2147483647 when not given
+fn random_int(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let int = |index: usize, default: i64| -> Result<i64, RenderError> {
+        match param(h, r, index)? {
+            Some(value) => {
+                value.as_i64().ok_or_else(|| invalid("random_int", "bounds must be integers"))
+            }
+            None => Ok(default),
+        }
+    };
+    let (min, max) = (int(0, 0)?, int(1, i32::MAX.into())?);
+    if min > max {
+        return Err(invalid("random_int", &format!("min {min} is greater than max {max}")));
+    }
+    let random: u64 = u64::from_le_bytes(random()?);
+    let offset: u64 = match max.abs_diff(min).checked_add(1) {
+        Some(span) => random % span,
+        None => random,
+    };
+    out.write(&min.wrapping_add(offset as i64).to_string())?;
+    Ok(())
+}
+
+/// A random alphanumeric string, with 16 characters unless a length is given, such as
+/// `{{random_string 32}}`. The length is at most [MAX_STRING_LENGTH].
+fn random_string(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let length: u64 = match param(h, r, 0)? {
+        Some(length) => length
+            .as_u64()
+            .ok_or_else(|| invalid("random_string", "length must be a positive integer"))?,
+        None => 16,
+    };
+    if length > MAX_STRING_LENGTH {
+        let reason: String = format!("length {length} is greater than {MAX_STRING_LENGTH}");
+        return Err(invalid("random_string", &reason));
+    }
+    let mut bytes: Vec<u8> = vec![0; length as usize];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| invalid("random_string", "no random source"))?;
+    let string: String = bytes
+        .iter()
+        .map(|b| ALPHANUMERIC[*b as usize % ALPHANUMERIC.len()] as char)
+        .collect();
+    out.write(&string)?;
+    Ok(())
+}
+
+/// Contents of a file, such as `{{file "payload.json"}}`, where a relative path is relative to the
+/// current directory, since helpers do not know which request file is rendered
+fn file(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    rc: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let path: &str = param(h, r, 0)?
+        .and_then(|path| path.as_str())
+        .ok_or_else(|| invalid("file", "a path is required"))?;
+    let content: String =
+        std::fs::read_to_string(path).map_err(|e| invalid("file", &format!("{path}: {e}")))?;
+    write(r, rc, out, &content)?;
+    Ok(())
+}
+
+/// Value of a parameter of a helper, if given. A parameter that refers to a missing value is an
+/// error, since it would otherwise be silently replaced with the default.
+fn param<'a>(
+    h: &'a Helper,
+    r: &Handlebars,
+    index: usize,
+) -> Result<Option<&'a Value>, RenderError> {
+    match h.param(index) {
+        Some(param) if r.strict_mode() && param.is_value_missing() => {
+            let name: &str = param.relative_path().map(|path| path.as_str()).unwrap_or_default();
+            Err(invalid(h.name(), &format!("missing value for key {name}")))
+        }
+        Some(param) => Ok(Some(param.value())),
+        None => Ok(None),
+    }
+}
+
+/// Write the output of a helper, escaped like any other value unless escaping is disabled, as in
+/// `{{{default NAME "x"}}}`
+fn write(r: &Handlebars, rc: &RenderContext, out: &mut dyn Output, value: &str) -> HelperResult {
+    match rc.is_disable_escape() {
+        true => out.write(value)?,
+        false => out.write(&r.get_escape_fn()(value))?,
+    }
+    Ok(())
+}
+
+fn random<const N: usize>() -> Result<[u8; N], RenderError> {
+    let mut bytes: [u8; N] = [0; N];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| RenderError::new("No random source"))?;
+    Ok(bytes)
+}
+
+fn invalid(helper: &str, reason: &str) -> RenderError {
+    RenderError::new(format!("`{helper}` helper: {reason}"))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use handlebars::{no_escape, Handlebars};
+
+    use super::register;
+
+    #[test]
+    fn test_helpers() {
+        let mut reg = Handlebars::new();
+        reg.register_escape_fn(no_escape);
+        reg.set_strict_mode(true);
+        register(&mut reg);
+        let render = |template: &str| {
+            reg.render_template(template, &serde_json::json!({"KEY": "key"})).unwrap()
+        };
+
+        assert_eq!("aGVsbG8gd29ybGQ=", render(r#"{{base64 "hello world"}}"#));
+        assert_eq!("PDw_Pz8-Pg", render(r#"{{base64url "<<???>>"}}"#));
+        assert_eq!("a%20b%26c~d", render(r#"{{urlencode "a b&c~d"}}"#));
+        assert_eq!(
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824",
+            render(r#"{{sha256 "hello"}}"#)
+        );
+        assert_eq!(
+            "90fbfcf15e74a36b89dbdb2a721d9aecffdfdddc5c83e27f7592594f71932481",
+            render(r#"{{hmac KEY "value"}}"#)
+        );
+        assert_eq!("key 20", render(r#"{{default KEY "1"}} {{default PAGE_SIZE 20}}"#));
+        assert_eq!(r#""say \"hi\"\n" 20"#, render(r#"{{json "say \"hi\"\n"}} {{json 20}}"#));
+        assert_eq!("KEY key", render("{{upper KEY}} {{lower \"KEY\"}}"));
+        assert_eq!("6", render("{{random_int 6 6}}"));
+        assert_eq!(32, render("{{random_string 32}}").len());
+        assert!(reg.render_template("{{random_string 4097}}", &()).is_err());
+        assert_eq!(4, render(r#"{{now "%Y"}}"#).len());
+
+        let uuid: String = render("{{uuid}}");
+        assert_eq!(36, uuid.len());
+        assert_eq!(Some('4'), uuid.chars().nth(14));
+
+        assert!(reg.render_template("{{upper MISSING}}", &()).is_err());
+        assert!(reg.render_template(r#"{{file "/does/not/exist"}}"#, &()).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use http::header::HeaderName;
+use http::{HeaderMap, HeaderValue};
+use httpx::HttpResponse;
+use serde::{Deserialize, Serialize};
+
+use crate::defaults::Defaults;
+use crate::error::FireError;
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// Max number of entries to keep, where the oldest entries are removed first
+const MAX_ENTRIES: usize = 500;
+
+/// An executed request and its response, as it is stored in the history. Each entry is stored as
+/// a line of JSON in the history file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: u64,
+    pub started: String,
+    pub duration_ms: u64,
+    pub file: PathBuf,
+    #[serde(default)]
+    pub environments: Vec<String>,
+    /// The rendered request file, which is what is executed again when the entry is replayed
+    pub content: String,
+    /// Rendered defaults from `_defaults.yml` files, which are applied to the request file
+    #[serde(default)]
+    pub defaults: Defaults,
+    pub request: Request,
+    pub response: Response,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub title: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    pub content_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Response {
+    pub version: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub content_type: Option<String>,
+}
+
+/// Where an executed request came from, so that it can be executed again
+pub struct Origin<'a> {
+    pub file: &'a Path,
+    pub content: &'a str,
+    pub environments: &'a [String],
+    pub defaults: &'a Defaults,
+}
+
+impl Entry {
+    pub fn new(
+        origin: &Origin,
+        request: Request,
+        response: Response,
+        started: DateTime<Utc>,
+        duration: Duration,
+    ) -> Entry {
+        Entry {
+            id: 0,
+            started: started.to_rfc3339(),
+            duration_ms: duration.as_millis() as u64,
+            file: origin.file.to_path_buf(),
+            environments: origin.environments.to_vec(),
+            content: origin.content.to_string(),
+            defaults: origin.defaults.clone(),
+            request,
+            response,
+        }
+    }
+
+    pub fn origin(&self) -> Origin<'_> {
+        Origin {
+            file: &self.file,
+            content: &self.content,
+            environments: &self.environments,
+            defaults: &self.defaults,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms)
+    }
+
+    /// Whether the entry matches a search term, which is matched against the title of the
+    /// request, the request file and the status of the response
+    pub fn matches(&self, search: &str) -> bool {
+        let search: String = search.to_lowercase();
+        self.request.title.to_lowercase().contains(&search)
+            || self.file.to_string_lossy().to_lowercase().contains(&search)
+            || self.response.status.to_string() == search
+    }
+
+    pub fn response(&self) -> HttpResponse {
+        HttpResponse::new(
+            self.response.version.clone(),
+            self.response.status,
+            header_map(&self.response.headers),
+            self.response.body.clone(),
+        )
+    }
+}
+
+pub fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_str().unwrap_or_default().to_string()))
+        .collect()
+}
+
+pub fn header_map(headers: &[(String, String)]) -> HeaderMap {
+    headers
+        .iter()
+        .filter_map(|(key, value)| {
+            let key = HeaderName::from_bytes(key.as_bytes()).ok()?;
+            let value = HeaderValue::from_str(value).ok()?;
+            Some((key, value))
+        })
+        .collect()
+}
+
+/// Directory where the history is stored, which is `$XDG_DATA_HOME/fire`, or
+/// `~/.local/share/fire` if `XDG_DATA_HOME` is not set
+pub fn dir() -> Result<PathBuf, FireError> {
+    let data_home: Option<PathBuf> = match std::env::var_os("XDG_DATA_HOME").map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => Some(dir),
+        _ => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")),
+    };
+    data_home
+        .map(|dir| dir.join("fire"))
+        .ok_or_else(|| FireError::Other(String::from("Unable to resolve directory for history")))
+}
+
+/// All entries in the history in a directory, with the oldest entry first
+pub fn entries(dir: &Path) -> Result<Vec<Entry>, FireError> {
+    let path: PathBuf = dir.join(HISTORY_FILE);
+    let io_err = |e: std::io::Error| FireError::GenericIO(format!("{}: {e}", path.display()));
+    let mut file: File = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(io_err(e)),
+    };
+
+    // Wait for any process that is writing to the history
+    file.lock_shared().map_err(io_err)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content).map_err(io_err)?;
+
+    Ok(parse(&content))
+}
+
+pub fn find(dir: &Path, id: u64) -> Result<Entry, FireError> {
+    entries(dir)?
+        .into_iter()
+        .find(|entry| entry.id == id)
+        .ok_or_else(|| FireError::Other(format!("No entry with id {id} in history")))
+}
+
+/// Add an entry to the history in a directory, returning the id of the new entry. The history
+/// file is locked while it is read and written, so that processes that save to the history at the
+/// same time never get the same id.
+pub fn record(dir: &Path, mut entry: Entry) -> Result<u64, FireError> {
+    let path: PathBuf = dir.join(HISTORY_FILE);
+    let io_err = |e: std::io::Error| FireError::GenericIO(format!("{}: {e}", path.display()));
+
+    std::fs::create_dir_all(dir).map_err(io_err)?;
+    let mut file: File = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(io_err)?;
+
+    // Requests and responses may contain secrets, so the file is only readable by its owner, even
+    // if it was created with other permissions
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let permissions = std::fs::Permissions::from_mode(0o600);
+        file.set_permissions(permissions).map_err(io_err)?;
+    }
+
+    file.lock().map_err(io_err)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content).map_err(io_err)?;
+    let mut entries: Vec<Entry> = parse(&content);
+    entry.id = entries.last().map(|last| last.id + 1).unwrap_or(1);
+    let id: u64 = entry.id;
+
+    if entries.len() < MAX_ENTRIES {
+        file.seek(SeekFrom::End(0)).map_err(io_err)?;
+        writeln!(file, "{}", serde_json::to_string(&entry).unwrap()).map_err(io_err)?;
+    } else {
+        entries.push(entry);
+        let keep: &[Entry] = &entries[entries.len() - MAX_ENTRIES..];
+        let content: String =
+            keep.iter().map(|entry| serde_json::to_string(entry).unwrap() + "\n").collect();
+        file.set_len(0).map_err(io_err)?;
+        file.seek(SeekFrom::Start(0)).map_err(io_err)?;
+        file.write_all(content.as_bytes()).map_err(io_err)?;
+    }
+
+    Ok(id)
+}
+
+fn parse(content: &str) -> Vec<Entry> {
+    content
+        .lines()
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                log::warn!("Skipping invalid entry in history: {e}");
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::Duration;
+
+    use chrono::Utc;
+    use httpx::test_util::TempDir;
+
+    use crate::defaults::Defaults;
+
+    use super::{entries, find, record, Entry, Origin, Request, Response};
+
+    #[test]
+    fn test_record_and_find_entries() {
+        let dir = TempDir::new("fire-history");
+        let dir: &Path = dir.path();
+
+        let origin = Origin {
+            file: Path::new("/requests/get_user.yml"),
+            content: "method: GET\nurl: https://example.com/users/1\n",
+            environments: &[String::from("dev")],
+            defaults: &Defaults::default(),
+        };
+
+        let entry = |status: u16| {
+            let request = Request {
+                title: String::from("GET https://example.com/users/1"),
+                headers: vec![(String::from("accept"), String::from("application/json"))],
+                body: None,
+                content_type: None,
+            };
+            let response = Response {
+                version: String::from("HTTP/1.1"),
+                status,
+                headers: Vec::new(),
+                body: String::from("{}"),
+                content_type: Some(String::from("application/json")),
+            };
+            Entry::new(&origin, request, response, Utc::now(), Duration::from_millis(42))
+        };
+
+        // An existing history file which is readable by others is made private
+        std::fs::write(dir.join(super::HISTORY_FILE), "").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let permissions = std::fs::Permissions::from_mode(0o644);
+            std::fs::set_permissions(dir.join(super::HISTORY_FILE), permissions).unwrap();
+        }
+
+        assert_eq!(1, record(dir, entry(200)).unwrap());
+        assert_eq!(2, record(dir, entry(404)).unwrap());
+
+        let second: Entry = find(dir, 2).unwrap();
+        assert_eq!(404, second.response.status);
+        assert_eq!(vec![String::from("dev")], second.environments);
+        assert!(second.matches("404"));
+        assert!(second.matches("get_user"));
+        assert!(!second.matches("orders"));
+        assert_eq!(2, entries(dir).unwrap().len());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = std::fs::metadata(dir.join(super::HISTORY_FILE)).unwrap();
+            assert_eq!(0o600, metadata.permissions().mode() & 0o777);
+        }
+    }
+}
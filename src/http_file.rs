@@ -0,0 +1,311 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+
+use http::header::HeaderName;
+use http::{HeaderMap, HeaderValue, Method};
+use httpx::HttpRequest;
+
+use crate::error::FireError;
+use crate::prop::{Property, Source};
+use crate::template::{substitution, SubstitutionError};
+
+const EXTENSIONS: [&str; 2] = ["http", "rest"];
+const SEPARATOR: &str = "###";
+
+/// A file in the `.http` format, as used by the REST Client extension for VS Code and by the
+/// JetBrains HTTP Client. A file can contain several requests, separated by `###`, and variable
+/// declarations in the form `@name = value`.
+#[derive(Debug)]
+pub struct HttpFile {
+    variables: Vec<(String, String)>,
+    requests: Vec<Block>,
+}
+
+/// A request in a `.http` file, where `content` is the (not yet rendered) request line, headers
+/// and body of the request, without any comments
+#[derive(Debug)]
+struct Block {
+    name: Option<String>,
+    content: String,
+}
+
+pub fn is_http_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| EXTENSIONS.contains(&ext))
+        .unwrap_or(false)
+}
+
+impl HttpFile {
+    pub fn parse(content: &str) -> HttpFile {
+        let mut variables: Vec<(String, String)> = Vec::new();
+        let mut requests: Vec<Block> = Vec::new();
+
+        let mut name: Option<String> = None;
+        let mut lines: Vec<&str> = Vec::new();
+
+        for line in content.lines().chain(std::iter::once(SEPARATOR)) {
+            if let Some(title) = line.strip_prefix(SEPARATOR) {
+                if let Some(block) = block(name.take(), &lines) {
+                    requests.push(block);
+                }
+                lines.clear();
+                // The JetBrains HTTP Client uses the text after the separator as name
+                let title: &str = title.trim_start_matches('#').trim();
+                name = Some(title.to_string()).filter(|title| !title.is_empty());
+                continue;
+            }
+
+            // Anything before the request line is either a comment or a declaration
+            if lines.is_empty() {
+                let trimmed: &str = line.trim();
+                if let Some(comment) = comment(trimmed) {
+                    if let Some(tag) = comment.strip_prefix("@name") {
+                        name = Some(tag.trim().to_string());
+                    }
+                } else if let Some((key, value)) = declaration(trimmed) {
+                    variables.push((key, value));
+                } else if !trimmed.is_empty() {
+                    lines.push(line);
+                }
+            } else {
+                lines.push(line);
+            }
+        }
+
+        HttpFile {
+            variables,
+            requests,
+        }
+    }
+
+    /// Select the request with the given name, or position (starting at 1) in the file. A name is
+    /// only needed when the file has more than one request.
+    pub fn select(&self, name: Option<&str>) -> Result<&str, FireError> {
+        let block: Option<&Block> = match name {
+            Some(name) => {
+                self.requests.iter().find(|block| block.name.as_deref() == Some(name)).or_else(
+                    || {
+                        let index: usize = name.parse().ok()?;
+                        self.requests.get(index.checked_sub(1)?)
+                    },
+                )
+            }
+            None if self.requests.len() == 1 => self.requests.first(),
+            None if self.requests.is_empty() => {
+                return Err(FireError::Other(String::from("No request in file")))
+            }
+            None => {
+                return Err(FireError::Other(format!(
+                    "File has {} requests, select one with --name: {}",
+                    self.requests.len(),
+                    self.names().join(", ")
+                )))
+            }
+        };
+
+        match block {
+            Some(block) => Ok(&block.content),
+            None => Err(FireError::Other(format!(
+                "No request named '{}', available requests are: {}",
+                name.unwrap_or_default(),
+                self.names().join(", ")
+            ))),
+        }
+    }
+
+    /// Names of all requests, where requests without a name are listed by their position
+    fn names(&self) -> Vec<String> {
+        self.requests
+            .iter()
+            .enumerate()
+            .map(|(i, block)| block.name.clone().unwrap_or_else(|| (i + 1).to_string()))
+            .collect()
+    }
+
+    /// The variables declared in the file that are used by `content`, the selected request, where
+    /// the value of a variable may refer to other variables, either from `props` or declared earlier
+    /// in the file. Declared variables take precedence over variables from environments files, but
+    /// not over variables given as arguments.
+    pub fn variables(
+        &self,
+        content: &str,
+        props: &[Property],
+        interactive: bool,
+        use_colors: bool,
+        trim: bool,
+    ) -> Result<Vec<Property>, SubstitutionError> {
+        // Only declarations that the request refers to, directly or through other declarations,
+        // are rendered, so that other requests in the file can use variables that are not set
+        let mut used: HashSet<&str> = mentions(content);
+        let mut needed: Vec<bool> = vec![false; self.variables.len()];
+        for (i, (key, value)) in self.variables.iter().enumerate().rev() {
+            if used.contains(key.as_str()) {
+                needed[i] = true;
+                used.extend(mentions(value));
+            }
+        }
+
+        let mut declared: Vec<Property> = Vec::with_capacity(self.variables.len());
+        for ((key, value), _) in self.variables.iter().zip(needed).filter(|(_, needed)| *needed) {
+            let vars: Vec<Property> = props.iter().chain(declared.iter()).cloned().collect();
+            let value: String = substitution(value.clone(), vars, interactive, use_colors, trim)?;
+            let prop = Property::new(key.clone(), value, Source::Declaration)
+                .map_err(|_| SubstitutionError::Rendering(format!("Invalid variable {key}")))?;
+            declared.push(prop);
+        }
+        Ok(declared)
+    }
+}
+
+/// Words within the templates of `content`. These include the names of all variables that are
+/// used, along with names of helpers and literals.
+fn mentions(content: &str) -> HashSet<&str> {
+    content
+        .split("{{")
+        .skip(1)
+        .filter_map(|template| template.split_once("}}").map(|(template, _)| template))
+        .flat_map(|template| {
+            template.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+fn block(name: Option<String>, lines: &[&str]) -> Option<Block> {
+    if lines.is_empty() {
+        return None;
+    }
+
+    let mut content: Vec<&str> = Vec::with_capacity(lines.len());
+    let mut in_body: bool = false;
+    for line in lines {
+        if !in_body && comment(line.trim()).is_some() {
+            continue;
+        }
+        in_body = in_body || line.trim().is_empty();
+        content.push(line);
+    }
+
+    Some(Block {
+        name,
+        content: content.join("\n").trim_end().to_string(),
+    })
+}
+
+fn comment(line: &str) -> Option<&str> {
+    line.strip_prefix('#').or_else(|| line.strip_prefix("//")).map(str::trim)
+}
+
+fn declaration(line: &str) -> Option<(String, String)> {
+    let (key, value) = line.strip_prefix('@')?.split_once('=')?;
+    Some((key.trim().to_string(), value.trim().to_string()))
+}
+
+/// Parse a rendered request from a `.http` file, which consists of a request line (optionally
+/// followed by lines starting with `?` or `&` that continue the query), headers and a body, where
+/// the body is separated from the headers by a blank line
+pub fn request(content: &str) -> Result<HttpRequest, FireError> {
+    let invalid = |msg: String| FireError::Other(format!("Invalid request: {msg}"));
+    let mut lines = content.lines().peekable();
+
+    let request_line: &str = lines.next().unwrap_or_default().trim();
+    let mut parts = request_line.split_whitespace();
+    let (method, mut url): (Method, String) = match (parts.next(), parts.next()) {
+        (Some(method), Some(url)) => match Method::from_str(method) {
+            Ok(method) => (method, url.to_string()),
+            Err(_) => return Err(invalid(format!("invalid method '{method}'"))),
+        },
+        // A request line with only an URL is a GET request
+        (Some(url), None) => (Method::GET, url.to_string()),
+        _ => return Err(invalid(String::from("missing request line"))),
+    };
+
+    while let Some(line) = lines.next_if(|line| line.trim_start().starts_with(['?', '&'])) {
+        url.push_str(line.trim());
+    }
+
+    let mut headers = HeaderMap::new();
+    for line in lines.by_ref() {
+        if line.trim().is_empty() {
+            break;
+        }
+        let (key, value) = match line.split_once(':') {
+            Some(header) => header,
+            None => return Err(invalid(format!("invalid header '{line}'"))),
+        };
+        let key = HeaderName::from_str(key.trim())
+            .map_err(|_| invalid(format!("invalid header name '{key}'")))?;
+        let value = HeaderValue::from_str(value.trim())
+            .map_err(|_| invalid(format!("invalid header value '{value}'")))?;
+        headers.append(key, value);
+    }
+
+    let body: String = lines.collect::<Vec<&str>>().join("\n");
+    let body: Option<String> = Some(body).filter(|body| !body.trim().is_empty());
+
+    Ok(HttpRequest::new(method, url, headers, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::Method;
+
+    use super::{request, HttpFile};
+    use crate::prop::Property;
+    use crate::template::substitution;
+
+    #[test]
+    fn test_parse_http_file() {
+        let file = r#"
+@host = https://api.example.com
+@team = core
+@token = {{TOKEN}}
+
+### List users
+GET {{host}}/users
+    ?team={{team}}
+    &limit=10
+Accept: application/json
+
+###
+# @name create
+// Create a new user
+POST {{host}}/users HTTP/1.1
+Content-Type: application/json
+
+{
+  "name": "fire"
+}
+"#;
+
+        let file = HttpFile::parse(file);
+        assert_eq!(2, file.requests.len());
+        assert_eq!(3, file.variables.len());
+        assert!(file.select(None).is_err());
+        assert!(file.select(Some("delete")).is_err());
+        assert_eq!(file.select(Some("2")).unwrap(), file.select(Some("create")).unwrap());
+
+        // Declarations that a request does not use are not rendered, so `TOKEN` is not needed
+        let render = |name: &str| {
+            let content: String = file.select(Some(name)).unwrap().to_string();
+            let vars: Vec<Property> = file.variables(&content, &[], false, false, false).unwrap();
+            request(&substitution(content, vars, false, false, false).unwrap()).unwrap()
+        };
+
+        let list = render("List users");
+        assert_eq!(Method::GET, list.method());
+        assert_eq!(
+            "https://api.example.com/users?team=core&limit=10",
+            list.url().unwrap().as_str()
+        );
+        assert_eq!(Some("application/json"), list.header("accept"));
+        assert_eq!(&None, list.body());
+
+        let create = render("create");
+        assert_eq!(Method::POST, create.method());
+        assert_eq!(Some("application/json"), create.header("content-type"));
+        assert_eq!(&Some(String::from("{\n  \"name\": \"fire\"\n}")), create.body());
+    }
+}
@@ -0,0 +1,211 @@
+use base64::Engine;
+use url::form_urlencoded;
+
+use super::RequestFile;
+use crate::error::FireError;
+
+/// Options that take a value, but which has no equivalent in a request file
+const IGNORED_WITH_VALUE: [&str; 22] = [
+    "-o",
+    "--output",
+    "-m",
+    "--max-time",
+    "--connect-timeout",
+    "-w",
+    "--write-out",
+    "--retry",
+    "-x",
+    "--proxy",
+    "--cacert",
+    "--capath",
+    "-E",
+    "--cert",
+    "--key",
+    "-c",
+    "--cookie-jar",
+    "--resolve",
+    "--max-redirs",
+    "-r",
+    "--range",
+    "--limit-rate",
+];
+
+/// Short options that take a value, which may be given directly after the option, like `-XPOST`
+const SHORT_WITH_VALUE: &str = "XHduAebFoEmwxcr";
+
+/// Parse a curl command into a request file
+pub fn parse(command: &str) -> Result<RequestFile, FireError> {
+    let words: Vec<String> = shell_words::split(command.trim())
+        .map_err(|e| FireError::Import(format!("Unable to parse curl command: {e}")))?;
+
+    let mut words = expand_short_options(words).into_iter().peekable();
+    if words.peek().map(String::as_str) == Some("curl") {
+        words.next();
+    }
+
+    let mut method: Option<String> = None;
+    let mut url: Option<String> = None;
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut data: Vec<String> = Vec::new();
+    let mut get: bool = false;
+
+    while let Some(word) = words.next() {
+        let mut value = || {
+            words
+                .next()
+                .ok_or_else(|| FireError::Import(format!("Missing value for option {word}")))
+        };
+
+        match word.as_str() {
+            "-X" | "--request" => method = Some(value()?),
+            "--url" => url = Some(value()?),
+            "-H" | "--header" => {
+                let header: String = value()?;
+                match header.split_once(':') {
+                    Some((key, value)) => headers.push((key.to_string(), value.to_string())),
+                    None => log::warn!("Ignoring invalid header '{header}'"),
+                }
+            }
+            "-d" | "--data" | "--data-ascii" | "--data-binary" => data.push(read_data(value()?)?),
+            "--data-raw" => data.push(value()?),
+            "--data-urlencode" => data.push(url_encode(&value()?)),
+            "--json" => {
+                data.push(read_data(value()?)?);
+                headers.push((String::from("content-type"), String::from("application/json")));
+                headers.push((String::from("accept"), String::from("application/json")));
+            }
+            "-u" | "--user" => {
+                let credentials = base64::engine::general_purpose::STANDARD.encode(value()?);
+                headers.push((String::from("authorization"), format!("Basic {credentials}")));
+            }
+            "-A" | "--user-agent" => headers.push((String::from("user-agent"), value()?)),
+            "-e" | "--referer" => headers.push((String::from("referer"), value()?)),
+            "-b" | "--cookie" => headers.push((String::from("cookie"), value()?)),
+            "-G" | "--get" => get = true,
+            "-I" | "--head" => method = Some(String::from("HEAD")),
+            "-F" | "--form" => {
+                return Err(FireError::Import(String::from(
+                    "Multipart form data (-F/--form) is not supported",
+                )))
+            }
+            option if IGNORED_WITH_VALUE.contains(&option) => {
+                let ignored: String = value()?;
+                log::info!("Ignoring option {option} {ignored}");
+            }
+            option if option.starts_with('-') => log::info!("Ignoring option {option}"),
+            positional => url = Some(positional.to_string()),
+        }
+    }
+
+    let url: String = url.ok_or_else(|| FireError::Import(String::from("No URL in command")))?;
+    let data: Option<String> = if data.is_empty() { None } else { Some(data.join("&")) };
+
+    let (url, body): (String, Option<String>) = match (get, data) {
+        (true, Some(query)) if url.contains('?') => (format!("{url}&{query}"), None),
+        (true, Some(query)) => (format!("{url}?{query}"), None),
+        (_, body) => (url, body),
+    };
+
+    let method: String = match (method, &body) {
+        (Some(method), _) => method,
+        (None, Some(_)) => String::from("POST"),
+        (None, None) => String::from("GET"),
+    };
+
+    let mut request = RequestFile::new(&method, &url);
+    for (key, value) in headers {
+        request.header(&key, &value);
+    }
+
+    if let Some(body) = body {
+        // Same default content type as curl uses for data
+        if !request.has_header("content-type") {
+            request.header("content-type", "application/x-www-form-urlencoded");
+        }
+        request.body(&body);
+    }
+
+    Ok(request)
+}
+
+/// Split combined short options, such as `-sSL` into `-s -S -L` and `-XPOST` into `-X POST`
+fn expand_short_options(words: Vec<String>) -> Vec<String> {
+    let mut expanded: Vec<String> = Vec::with_capacity(words.len());
+    for word in words {
+        let is_short: bool = word.starts_with('-') && !word.starts_with("--") && word.len() > 2;
+        if !is_short {
+            expanded.push(word);
+            continue;
+        }
+
+        for (i, c) in word.char_indices().skip(1) {
+            expanded.push(format!("-{c}"));
+            if SHORT_WITH_VALUE.contains(c) {
+                let value: &str = &word[i + c.len_utf8()..];
+                if !value.is_empty() {
+                    expanded.push(value.to_string());
+                }
+                break;
+            }
+        }
+    }
+    expanded
+}
+
+/// Data starting with `@` is read from a file, just like curl does
+fn read_data(data: String) -> Result<String, FireError> {
+    match data.strip_prefix('@') {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| FireError::Import(format!("Unable to read data from {path}: {e}"))),
+        None => Ok(data),
+    }
+}
+
+fn url_encode(data: &str) -> String {
+    match data.split_once('=') {
+        Some((name, value)) => {
+            let value: String = form_urlencoded::byte_serialize(value.as_bytes()).collect();
+            format!("{name}={value}")
+        }
+        None => form_urlencoded::byte_serialize(data.as_bytes()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn test_parse_curl_command() {
+        let command = r#"curl -sSL -XPOST 'https://api.example.com/users?active=true' \
+            -H 'Content-Type: application/json' \
+            -H "Authorization: Bearer abc123" \
+            --data-raw '{"name":"fire","age":1}'"#;
+
+        let mut request = parse(command).unwrap();
+        let variables: Vec<String> = request.template_secrets();
+        let expected = r#"method: POST
+url: https://api.example.com/users?active=true
+headers:
+  content-type: application/json
+  authorization: Bearer {{AUTHORIZATION}}
+body: '{"name":"fire","age":1}'
+"#;
+
+        assert_eq!(expected, request.to_yaml());
+        assert_eq!(vec![String::from("AUTHORIZATION")], variables);
+    }
+
+    #[test]
+    fn test_parse_curl_command_with_query_data() {
+        let command = "curl -G https://example.com/search -u user:pass --data-urlencode 'q=a b'";
+        let request = parse(command).unwrap();
+        let expected = r#"method: GET
+url: https://example.com/search?q=a+b
+headers:
+  authorization: Basic dXNlcjpwYXNz
+"#;
+
+        assert_eq!(expected, request.to_yaml());
+    }
+}
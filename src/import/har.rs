@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+
+use url::Url;
+
+use super::RequestFile;
+use crate::error::FireError;
+use crate::har::{Entry, Har};
+
+/// Headers that are either set by fire itself, or which only make sense for the browser that
+/// captured the request
+const SKIPPED_HEADERS: [&str; 5] = [
+    "host",
+    "content-length",
+    "connection",
+    "accept-encoding",
+    "transfer-encoding",
+];
+
+const MAX_NAME_LEN: usize = 64;
+
+/// Create a request file from each entry in a HAR log, optionally only for entries where the host
+/// of the URL is `host`. Returns the request files, with the file name each should be saved as.
+pub fn parse(path: &Path, host: Option<&str>) -> Result<Vec<(PathBuf, RequestFile)>, FireError> {
+    let har: Har = Har::from_file(path)?;
+
+    let requests = har
+        .log
+        .entries
+        .iter()
+        .filter(|entry| match host {
+            Some(host) => Url::parse(&entry.request.url)
+                .map(|url| url.host_str() == Some(host))
+                .unwrap_or(false),
+            None => true,
+        })
+        .enumerate()
+        .map(|(index, entry)| (file_name(index + 1, entry), request(entry)))
+        .collect();
+
+    Ok(requests)
+}
+
+fn request(entry: &Entry) -> RequestFile {
+    let mut request = RequestFile::new(&entry.request.method, &entry.request.url);
+
+    entry
+        .request
+        .headers
+        .iter()
+        // HTTP/2 pseudo-headers, like `:authority`, are not actual headers
+        .filter(|header| !header.name.starts_with(':'))
+        .filter(|header| !SKIPPED_HEADERS.contains(&header.name.to_lowercase().as_str()))
+        .for_each(|header| request.header(&header.name, &header.value));
+
+    if let Some(post_data) = &entry.request.post_data {
+        match &post_data.text {
+            Some(text) => request.body(text),
+            None => {
+                let params: String = url::form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(post_data.params.iter().map(|p| (&p.name, &p.value)))
+                    .finish();
+                request.body(&params);
+            }
+        }
+        if !request.has_header("content-type") && !post_data.mime_type.is_empty() {
+            request.header("content-type", &post_data.mime_type);
+        }
+    }
+
+    request
+}
+
+/// A file name such as `003_post_api_users.yml`, where the prefix keeps the files in the same
+/// order as in the HAR log
+fn file_name(index: usize, entry: &Entry) -> PathBuf {
+    let path: String = Url::parse(&entry.request.url)
+        .map(|url| url.path().to_string())
+        .unwrap_or_default();
+
+    let name: String = path
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("_")
+        .chars()
+        .take(MAX_NAME_LEN)
+        .collect();
+
+    let method: String = entry.request.method.to_lowercase();
+    if name.is_empty() {
+        PathBuf::from(format!("{index:03}_{method}.yml"))
+    } else {
+        PathBuf::from(format!("{index:03}_{method}_{name}.yml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{file_name, request};
+    use crate::har::Har;
+
+    #[test]
+    fn test_request_from_har_entry() {
+        let har = r#"{
+            "log": {
+                "version": "1.2",
+                "creator": { "name": "Firefox", "version": "128.0" },
+                "entries": [{
+                    "startedDateTime": "2024-05-01T12:00:00.000Z",
+                    "time": 42.0,
+                    "request": {
+                        "method": "POST",
+                        "url": "https://api.example.com/v1/users?team=core",
+                        "httpVersion": "HTTP/2",
+                        "headers": [
+                            { "name": ":authority", "value": "api.example.com" },
+                            { "name": "Accept", "value": "application/json" },
+                            { "name": "Content-Length", "value": "15" }
+                        ],
+                        "postData": { "mimeType": "application/json", "text": "{\"name\":\"fire\"}" }
+                    },
+                    "response": { "status": 201, "content": { "size": 0 } },
+                    "timings": { "send": 1, "wait": 40, "receive": 1 }
+                }]
+            }
+        }"#;
+
+        let har: Har = serde_json::from_str(har).unwrap();
+        let entry = &har.log.entries[0];
+        let expected = r#"method: POST
+url: https://api.example.com/v1/users?team=core
+headers:
+  accept: application/json
+  content-type: application/json
+body: '{"name":"fire"}'
+"#;
+
+        assert_eq!(expected, request(entry).to_yaml());
+        assert_eq!(PathBuf::from("007_post_v1_users.yml"), file_name(7, entry));
+    }
+}
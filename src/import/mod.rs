@@ -0,0 +1,218 @@
+mod curl;
+mod har;
+mod openapi;
+mod postman;
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::args::Import;
+use crate::error::FireError;
+use crate::import::openapi::OpenApi;
+use crate::import::postman::PostmanFile;
+
+/// Header names, or parts of header names, for headers that most likely contain secrets
+const SECRET_HEADERS: [&str; 7] = [
+    "authorization",
+    "cookie",
+    "token",
+    "secret",
+    "password",
+    "api-key",
+    "apikey",
+];
+
+/// Authorization schemes that are kept as they are when the credentials are replaced with a
+/// template variable, so `Bearer abc123` becomes `Bearer {{AUTHORIZATION}}`
+const AUTH_SCHEMES: [&str; 4] = ["Bearer ", "Basic ", "Token ", "token "];
+
+/// A request file, with the same structure as the one that is read by [httpx::HttpRequest]
+#[derive(Debug, Serialize)]
+pub struct RequestFile {
+    method: String,
+    url: String,
+    #[serde(skip_serializing_if = "serde_yaml::Mapping::is_empty")]
+    headers: serde_yaml::Mapping,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+}
+
+impl RequestFile {
+    pub fn new(method: &str, url: &str) -> RequestFile {
+        RequestFile {
+            method: method.to_uppercase(),
+            url: url.to_string(),
+            headers: serde_yaml::Mapping::new(),
+            body: None,
+        }
+    }
+
+    /// Add a header, where any previous value for the same header is joined with the new value
+    pub fn header(&mut self, key: &str, value: &str) {
+        let key = serde_yaml::Value::String(key.trim().to_lowercase());
+        let value: String = match self.headers.get(&key).and_then(|v| v.as_str()) {
+            Some(previous) => format!("{previous}, {}", value.trim()),
+            None => value.trim().to_string(),
+        };
+        self.headers.insert(key, serde_yaml::Value::String(value));
+    }
+
+    pub fn has_header(&self, key: &str) -> bool {
+        self.headers.contains_key(key.to_lowercase().as_str())
+    }
+
+    /// Set the body, which is kept as it is, so that the request file sends the same body as the
+    /// imported request
+    pub fn body(&mut self, body: &str) {
+        self.body = Some(body.to_string());
+    }
+
+    /// Replace the values of any headers that seem to contain secrets with template variables,
+    /// returning the names of the variables that were introduced
+    pub fn template_secrets(&mut self) -> Vec<String> {
+        let mut variables: Vec<String> = Vec::new();
+        for (key, value) in self.headers.iter_mut() {
+            let key: &str = key.as_str().unwrap_or_default();
+            if !SECRET_HEADERS.iter().any(|secret| key.contains(secret)) {
+                continue;
+            }
+
+            let variable: String = key.replace('-', "_").to_uppercase();
+            let current: &str = value.as_str().unwrap_or_default();
+            let templated: String = match AUTH_SCHEMES.iter().find(|s| current.starts_with(*s)) {
+                Some(scheme) => format!("{scheme}{{{{{variable}}}}}"),
+                None => format!("{{{{{variable}}}}}"),
+            };
+
+            *value = serde_yaml::Value::String(templated);
+            variables.push(variable);
+        }
+        variables
+    }
+
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(self).unwrap()
+    }
+}
+
+pub fn import(import: &Import) -> Result<(), FireError> {
+    match import {
+        Import::Curl {
+            command,
+            output,
+            template_secrets,
+        } => {
+            let command: String = match command {
+                Some(command) => command.clone(),
+                None => read_stdin()?,
+            };
+            let mut request: RequestFile = curl::parse(&command)?;
+            if *template_secrets {
+                report_variables(&request.template_secrets());
+            }
+            write(&request.to_yaml(), output.as_deref())
+        }
+        Import::Har {
+            file,
+            host,
+            output_dir,
+            template_secrets,
+        } => {
+            let requests: Vec<(PathBuf, RequestFile)> = har::parse(file, host.as_deref())?;
+            if requests.is_empty() {
+                return Err(FireError::Import(format!("No matching entries in {:?}", file)));
+            }
+
+            let mut variables: Vec<String> = Vec::new();
+            for (name, mut request) in requests {
+                if *template_secrets {
+                    variables.extend(request.template_secrets());
+                }
+                write(&request.to_yaml(), Some(&output_dir.join(name)))?;
+            }
+
+            variables.sort();
+            variables.dedup();
+            report_variables(&variables);
+            Ok(())
+        }
+        Import::Postman {
+            collection,
+            environment,
+            output_dir,
+        } => {
+            let mut files: Vec<PostmanFile> = postman::parse_collection(collection)?;
+            for environment in environment {
+                files.extend(postman::parse_environment(environment)?);
+            }
+
+            for file in files {
+                match file {
+                    PostmanFile::Request(path, request) => {
+                        write(&request.to_yaml(), Some(&output_dir.join(path)))?
+                    }
+                    PostmanFile::Environment(path, content) => {
+                        write(&content, Some(&output_dir.join(path)))?
+                    }
+                }
+            }
+            Ok(())
+        }
+        Import::Openapi { spec, output_dir } => {
+            let openapi: OpenApi = openapi::parse(spec)?;
+            if openapi.requests.is_empty() {
+                return Err(FireError::Import(format!("No operations in {:?}", spec)));
+            }
+
+            for (path, request) in &openapi.requests {
+                write(&request.to_yaml(), Some(&output_dir.join(path)))?;
+            }
+
+            // An existing environments file may already have values that should not be lost
+            let env: PathBuf = output_dir.join(".env");
+            if env.exists() {
+                log::warn!("Not overwriting existing file {:?}", env);
+                Ok(())
+            } else {
+                write(&openapi.env, Some(&env))
+            }
+        }
+    }
+}
+
+fn read_stdin() -> Result<String, FireError> {
+    let mut input = String::new();
+    std::io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|e| FireError::GenericIO(e.to_string()))?;
+    Ok(input)
+}
+
+fn report_variables(variables: &[String]) {
+    if !variables.is_empty() {
+        eprintln!(
+            "Values for {} must be given in an environment file or with --variable",
+            variables.join(", ")
+        );
+    }
+}
+
+/// Write `content` to the file at `output`, or to stdout if no output file is given
+fn write(content: &str, output: Option<&Path>) -> Result<(), FireError> {
+    match output {
+        Some(path) => {
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                std::fs::create_dir_all(dir).map_err(|e| FireError::GenericIO(e.to_string()))?;
+            }
+            std::fs::write(path, content).map_err(|e| FireError::GenericIO(e.to_string()))?;
+            log::info!("Wrote file {:?}", path);
+            Ok(())
+        }
+        None => {
+            print!("{content}");
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde_json::{Map, Value};
+
+use super::RequestFile;
+use crate::error::FireError;
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+const BASE_URL: &str = "BASE_URL";
+
+/// Max depth when generating examples from schemas, which may be recursive
+const MAX_DEPTH: usize = 8;
+
+lazy_static! {
+    static ref PATH_PARAM_REGEX: Regex = Regex::new(r"\{([^{}]+)\}").unwrap();
+}
+
+/// Request files generated from an OpenAPI specification, together with the content of an
+/// environments file which lists every variable that the requests need
+pub struct OpenApi {
+    pub requests: Vec<(PathBuf, RequestFile)>,
+    pub env: String,
+}
+
+/// Generate one request file per operation in an OpenAPI 3 specification (JSON or YAML). Request
+/// files are grouped in directories by the first tag of each operation.
+pub fn parse(path: &Path) -> Result<OpenApi, FireError> {
+    let content: String = std::fs::read_to_string(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => FireError::FileNotFound(path.to_path_buf()),
+        _ => FireError::GenericIO(e.to_string()),
+    })?;
+
+    // JSON is valid YAML, so both formats can be parsed the same way
+    let spec: Value = serde_yaml::from_str(&content)
+        .map_err(|e| FireError::Import(format!("Invalid specification {:?}: {e}", path)))?;
+
+    match spec.get("openapi").and_then(Value::as_str) {
+        Some(version) if version.starts_with('3') => Ok(generate(&spec)),
+        _ => Err(FireError::Import(format!("{:?} is not an OpenAPI 3 specification", path))),
+    }
+}
+
+fn generate(spec: &Value) -> OpenApi {
+    let mut requests: Vec<(PathBuf, RequestFile)> = Vec::new();
+    let mut variables: BTreeSet<String> = BTreeSet::new();
+    let mut names: HashSet<PathBuf> = HashSet::new();
+
+    let paths: Map<String, Value> =
+        spec.get("paths").and_then(Value::as_object).cloned().unwrap_or_default();
+
+    for (path, item) in paths.iter() {
+        let shared: Vec<Value> = parameters(spec, item.get("parameters"));
+        for method in METHODS {
+            let operation: &Value = match item.get(method) {
+                Some(operation) => operation,
+                None => continue,
+            };
+
+            let mut vars: BTreeSet<String> = BTreeSet::new();
+            let request: RequestFile = request(spec, path, method, operation, &shared, &mut vars);
+            variables.extend(vars);
+
+            let name: String = operation
+                .get("operationId")
+                .and_then(Value::as_str)
+                .map(file_name)
+                .unwrap_or_else(|| file_name(&format!("{method}_{path}")));
+
+            let dir: PathBuf = operation
+                .pointer("/tags/0")
+                .and_then(Value::as_str)
+                .map(|tag| PathBuf::from(file_name(tag)))
+                .unwrap_or_default();
+
+            let mut file: PathBuf = dir.join(format!("{name}.yml"));
+            let mut n: usize = 2;
+            while names.contains(&file) {
+                file = dir.join(format!("{name}_{n}.yml"));
+                n += 1;
+            }
+            names.insert(file.clone());
+            requests.push((file, request));
+        }
+    }
+
+    OpenApi {
+        requests,
+        env: env(spec, &variables),
+    }
+}
+
+fn request(
+    spec: &Value,
+    path: &str,
+    method: &str,
+    operation: &Value,
+    shared: &[Value],
+    variables: &mut BTreeSet<String>,
+) -> RequestFile {
+    variables.insert(String::from(BASE_URL));
+
+    // Parameters of the operation override parameters with the same name and location on the path
+    let mut params: Vec<Value> = parameters(spec, operation.get("parameters"));
+    for param in shared {
+        let same = |p: &Value| p.get("name") == param.get("name") && p.get("in") == param.get("in");
+        if !params.iter().any(same) {
+            params.push(param.clone());
+        }
+    }
+
+    let url_path: String = PATH_PARAM_REGEX
+        .replace_all(path, |caps: &Captures| {
+            let variable: String = variable_name(&caps[1]);
+            let template: String = format!("{{{{{variable}}}}}");
+            variables.insert(variable);
+            template
+        })
+        .to_string();
+
+    let required = |param: &&Value| param.get("required").and_then(Value::as_bool) == Some(true);
+    let location = |loc: &'static str| {
+        move |param: &&Value| param.get("in").and_then(Value::as_str) == Some(loc)
+    };
+
+    let query: Vec<String> = params
+        .iter()
+        .filter(location("query"))
+        .filter(required)
+        .filter_map(|param| param.get("name").and_then(Value::as_str))
+        .map(|name| {
+            let variable: String = variable_name(name);
+            let query: String = format!("{name}={{{{{variable}}}}}");
+            variables.insert(variable);
+            query
+        })
+        .collect();
+
+    let url: String = if query.is_empty() {
+        format!("{{{{{BASE_URL}}}}}{url_path}")
+    } else {
+        format!("{{{{{BASE_URL}}}}}{url_path}?{}", query.join("&"))
+    };
+
+    let mut request = RequestFile::new(method, &url);
+
+    if let Some(accept) = accept(operation) {
+        request.header("accept", accept);
+    }
+
+    for name in params
+        .iter()
+        .filter(location("header"))
+        .filter(required)
+        .filter_map(|p| p.get("name")?.as_str())
+    {
+        let variable: String = variable_name(name);
+        request.header(name, &format!("{{{{{variable}}}}}"));
+        variables.insert(variable);
+    }
+
+    for (name, scheme) in security(spec, operation) {
+        let variable: String = variable_name(&name);
+        request.header(&name, &format!("{scheme}{{{{{variable}}}}}"));
+        variables.insert(variable);
+    }
+
+    if let Some((content_type, body)) = body(spec, operation) {
+        request.header("content-type", &content_type);
+        request.body(&body);
+    }
+
+    request
+}
+
+/// Resolve all parameters, where parameters can be references to shared components
+fn parameters(spec: &Value, params: Option<&Value>) -> Vec<Value> {
+    params
+        .and_then(Value::as_array)
+        .map(|params| params.iter().map(|param| resolve(spec, param).clone()).collect())
+        .unwrap_or_default()
+}
+
+/// Resolve a local reference, like `#/components/schemas/User`
+fn resolve<'a>(spec: &'a Value, value: &'a Value) -> &'a Value {
+    match value.get("$ref").and_then(Value::as_str).and_then(|r| r.strip_prefix('#')) {
+        Some(pointer) => spec.pointer(pointer).unwrap_or(&Value::Null),
+        None => value,
+    }
+}
+
+/// The first content type of a successful response, if any
+fn accept(operation: &Value) -> Option<&str> {
+    let responses: &Map<String, Value> = operation.get("responses")?.as_object()?;
+    responses
+        .iter()
+        .filter(|(status, _)| status.starts_with('2'))
+        .filter_map(|(_, response)| response.get("content")?.as_object())
+        .flat_map(|content| content.keys())
+        .map(String::as_str)
+        .next()
+}
+
+/// Headers for the security requirements of an operation, or of the whole API if the operation
+/// has no requirements of its own. Each header is given as its name and the authorization scheme
+/// that should precede the credentials, if any.
+fn security(spec: &Value, operation: &Value) -> Vec<(String, &'static str)> {
+    let requirements: &[Value] = match operation.get("security").or_else(|| spec.get("security")) {
+        Some(Value::Array(requirements)) => requirements,
+        _ => return Vec::new(),
+    };
+
+    // Only the first alternative of the security requirements is used
+    let schemes: Vec<&String> = match requirements.first().and_then(Value::as_object) {
+        Some(requirement) => requirement.keys().collect(),
+        None => return Vec::new(),
+    };
+
+    schemes
+        .into_iter()
+        .filter_map(|name| spec.pointer(&format!("/components/securitySchemes/{name}")))
+        .filter_map(|scheme| {
+            let kind: &str = scheme.get("type")?.as_str()?;
+            match (kind, scheme.get("scheme").and_then(Value::as_str)) {
+                ("http", Some("bearer")) | ("oauth2", _) | ("openIdConnect", _) => {
+                    Some((String::from("authorization"), "Bearer "))
+                }
+                ("http", Some("basic")) => Some((String::from("authorization"), "Basic ")),
+                ("apiKey", _) if scheme.get("in").and_then(Value::as_str) == Some("header") => {
+                    Some((scheme.get("name")?.as_str()?.to_string(), ""))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Content type and an example body of the request body of an operation, where JSON is preferred
+/// if several content types are supported
+fn body(spec: &Value, operation: &Value) -> Option<(String, String)> {
+    let request_body: &Value = resolve(spec, operation.get("requestBody")?);
+    let content: &Map<String, Value> = request_body.get("content")?.as_object()?;
+    let (content_type, media) = content
+        .iter()
+        .find(|(content_type, _)| content_type.contains("json"))
+        .or_else(|| content.iter().next())?;
+
+    let example: Value = match (media.get("example"), media.get("examples")) {
+        (Some(example), _) => example.clone(),
+        (None, Some(Value::Object(examples))) => examples
+            .values()
+            .next()
+            .map(|example| resolve(spec, example))
+            .and_then(|example| example.get("value"))
+            .cloned()
+            .unwrap_or(Value::Null),
+        _ => example(spec, media.get("schema").unwrap_or(&Value::Null), 0),
+    };
+
+    let body: String = match example {
+        Value::String(text) => text,
+        Value::Null => return None,
+        json => serde_json::to_string_pretty(&json).unwrap() + "\n",
+    };
+
+    Some((content_type.clone(), body))
+}
+
+/// Generate an example value from a schema
+fn example(spec: &Value, schema: &Value, depth: usize) -> Value {
+    if depth > MAX_DEPTH {
+        return Value::Null;
+    }
+
+    let schema: &Value = resolve(spec, schema);
+    if let Some(example) = schema.get("example").or_else(|| schema.get("default")) {
+        return example.clone();
+    }
+    if let Some(first) = schema.pointer("/enum/0") {
+        return first.clone();
+    }
+    if let Some(Value::Array(all)) = schema.get("allOf") {
+        let mut merged: Map<String, Value> = Map::new();
+        for part in all {
+            if let Value::Object(part) = example(spec, part, depth + 1) {
+                merged.extend(part);
+            }
+        }
+        return Value::Object(merged);
+    }
+    if let Some(first) = schema.pointer("/oneOf/0").or_else(|| schema.pointer("/anyOf/0")) {
+        return example(spec, first, depth + 1);
+    }
+
+    let kind: &str = match schema.get("type") {
+        Some(Value::String(kind)) => kind,
+        // OpenAPI 3.1 allows a list of types, such as `["string", "null"]`
+        Some(Value::Array(kinds)) => {
+            kinds.iter().filter_map(Value::as_str).find(|k| *k != "null").unwrap_or("null")
+        }
+        _ if schema.get("properties").is_some() => "object",
+        _ => "null",
+    };
+
+    match kind {
+        "object" => {
+            let properties: Map<String, Value> = schema
+                .get("properties")
+                .and_then(Value::as_object)
+                .map(|properties| {
+                    properties
+                        .iter()
+                        .map(|(name, property)| (name.clone(), example(spec, property, depth + 1)))
+                        .collect()
+                })
+                .unwrap_or_default();
+            Value::Object(properties)
+        }
+        "array" => {
+            let item: Value = example(spec, schema.get("items").unwrap_or(&Value::Null), depth + 1);
+            Value::Array(vec![item])
+        }
+        "string" => {
+            let example: &str = match schema.get("format").and_then(Value::as_str) {
+                Some("date-time") => "2024-01-01T12:00:00Z",
+                Some("date") => "2024-01-01",
+                Some("uuid") => "00000000-0000-0000-0000-000000000000",
+                Some("email") => "user@example.com",
+                Some("uri") | Some("url") => "https://example.com",
+                _ => "string",
+            };
+            Value::String(example.to_string())
+        }
+        "integer" => Value::from(0),
+        "number" => Value::from(0.0),
+        "boolean" => Value::Bool(true),
+        _ => Value::Null,
+    }
+}
+
+/// Content of an environments file with every variable that is needed by the requests. Only the
+/// base URL has a value, from the first server in the specification, the other variables are
+/// commented out so they are still reported as missing until they are given a value.
+fn env(spec: &Value, variables: &BTreeSet<String>) -> String {
+    let server: Option<&str> = spec.pointer("/servers/0/url").and_then(Value::as_str);
+
+    variables
+        .iter()
+        .map(|variable| match (variable.as_str(), server) {
+            (BASE_URL, Some(url)) => format!("{BASE_URL}={url}\n"),
+            (variable, _) => format!("# {variable}=\n"),
+        })
+        .collect()
+}
+
+/// Template variables are written in upper case, like `userId` becomes `USER_ID`
+fn variable_name(name: &str) -> String {
+    let mut variable = String::with_capacity(name.len() + 4);
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && previous.map(|p| p.is_ascii_lowercase()).unwrap_or(false) {
+            variable.push('_');
+        }
+        variable.push(if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' });
+        previous = Some(c);
+    }
+    variable
+}
+
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect::<String>()
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::generate;
+
+    #[test]
+    fn test_generate_requests_from_spec() {
+        let spec = r##"
+            openapi: 3.0.3
+            info: { title: Users, version: "1.0" }
+            servers:
+              - url: https://api.example.com/v1
+            components:
+              securitySchemes:
+                token: { type: http, scheme: bearer }
+              schemas:
+                User:
+                  type: object
+                  properties:
+                    name: { type: string, example: fire }
+                    age: { type: integer }
+            security:
+              - token: []
+            paths:
+              /teams/{teamId}/users:
+                parameters:
+                  - { name: teamId, in: path, required: true, schema: { type: string } }
+                post:
+                  operationId: createUser
+                  tags: [users]
+                  parameters:
+                    - { name: dryRun, in: query, required: true, schema: { type: boolean } }
+                    - { name: X-Request-Id, in: header, required: true, schema: { type: string } }
+                  requestBody:
+                    content:
+                      application/json:
+                        schema: { $ref: "#/components/schemas/User" }
+                  responses:
+                    "201":
+                      content:
+                        application/json: {}
+        "##;
+
+        let spec: serde_json::Value = serde_yaml::from_str(spec).unwrap();
+        let openapi = generate(&spec);
+        let (path, request) = &openapi.requests[0];
+
+        let expected = r#"method: POST
+url: '{{BASE_URL}}/teams/{{TEAM_ID}}/users?dryRun={{DRY_RUN}}'
+headers:
+  accept: application/json
+  x-request-id: '{{X_REQUEST_ID}}'
+  authorization: Bearer {{AUTHORIZATION}}
+  content-type: application/json
+body: |
+  {
+    "age": 0,
+    "name": "fire"
+  }
+"#;
+
+        assert_eq!(PathBuf::from("users/createUser.yml"), *path);
+        assert_eq!(expected, request.to_yaml());
+
+        let expected_env = "# AUTHORIZATION=\nBASE_URL=https://api.example.com/v1\n# DRY_RUN=\n\
+            # TEAM_ID=\n# X_REQUEST_ID=\n";
+        assert_eq!(expected_env, openapi.env);
+    }
+}
@@ -0,0 +1,431 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+use super::RequestFile;
+use crate::error::FireError;
+
+lazy_static! {
+    static ref VAR_REGEX: Regex = Regex::new(r"\{\{\s*([^{}]+?)\s*\}\}").unwrap();
+}
+
+/// A file created from a Postman collection or environment, with its path relative to the output
+/// directory
+pub enum PostmanFile {
+    Request(PathBuf, RequestFile),
+    Environment(PathBuf, String),
+}
+
+/// Convert a Postman (v2.1) collection into request files, where folders in the collection are
+/// mirrored as directories. Any collection variables are put in a global `.env` file.
+pub fn parse_collection(path: &Path) -> Result<Vec<PostmanFile>, FireError> {
+    let collection: Value = read_json(path)?;
+    if collection.get("item").is_none() {
+        return Err(FireError::Import(format!("{:?} is not a Postman collection", path)));
+    }
+
+    let mut files: Vec<PostmanFile> = Vec::new();
+    items(&collection, Path::new(""), collection.get("auth"), &mut files);
+
+    let variables: String = env_file(collection.get("variable"), |_| true);
+    if !variables.is_empty() {
+        files.push(PostmanFile::Environment(PathBuf::from(".env"), variables));
+    }
+
+    Ok(files)
+}
+
+/// Convert a Postman environment into a `<name>.env` file, and a `<name>.sec` file for any
+/// variables of the type `secret`
+pub fn parse_environment(path: &Path) -> Result<Vec<PostmanFile>, FireError> {
+    let environment: Value = read_json(path)?;
+    let name: String = match environment.get("name").and_then(Value::as_str) {
+        Some(name) => file_name(name).to_lowercase(),
+        None => return Err(FireError::Import(format!("{:?} is not a Postman environment", path))),
+    };
+
+    let values: Option<&Value> = environment.get("values");
+    let is_secret = |value: &Value| value.get("type").and_then(Value::as_str) == Some("secret");
+
+    let mut files: Vec<PostmanFile> = Vec::with_capacity(2);
+    let env: String = env_file(values, |value| !is_secret(value));
+    if !env.is_empty() {
+        files.push(PostmanFile::Environment(PathBuf::from(format!("{name}.env")), env));
+    }
+    let sec: String = env_file(values, is_secret);
+    if !sec.is_empty() {
+        files.push(PostmanFile::Environment(PathBuf::from(format!("{name}.sec")), sec));
+    }
+
+    Ok(files)
+}
+
+fn read_json(path: &Path) -> Result<Value, FireError> {
+    let content: String = std::fs::read_to_string(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => FireError::FileNotFound(path.to_path_buf()),
+        _ => FireError::GenericIO(e.to_string()),
+    })?;
+
+    serde_json::from_str(&content)
+        .map_err(|e| FireError::Import(format!("Invalid JSON in {:?}: {e}", path)))
+}
+
+/// Recursively convert the items of a collection or folder. Authentication is inherited from the
+/// closest parent that has any authentication configured.
+fn items(parent: &Value, dir: &Path, auth: Option<&Value>, files: &mut Vec<PostmanFile>) {
+    let children: &[Value] = match parent.get("item").and_then(Value::as_array) {
+        Some(children) => children,
+        None => return,
+    };
+
+    let mut names: HashSet<String> = HashSet::with_capacity(children.len());
+    for item in children {
+        let name: String =
+            item.get("name").and_then(Value::as_str).map(file_name).unwrap_or_default();
+        let name: String =
+            unique(if name.is_empty() { String::from("request") } else { name }, &mut names);
+        let auth: Option<&Value> = item.get("auth").or(auth);
+
+        if item.get("item").is_some() {
+            items(item, &dir.join(name), auth, files);
+        } else if let Some(request) = item.get("request") {
+            let path: PathBuf = dir.join(format!("{name}.yml"));
+            files.push(PostmanFile::Request(path, self::request(request, auth)));
+        }
+    }
+}
+
+fn request(request: &Value, auth: Option<&Value>) -> RequestFile {
+    // A request can be given as just the URL, in which case it is a GET request
+    if let Some(url) = request.as_str() {
+        return RequestFile::new("GET", &variables(url));
+    }
+
+    let method: &str = request.get("method").and_then(Value::as_str).unwrap_or("GET");
+    let mut url: String = request.get("url").map(self::url).unwrap_or_default();
+
+    let mut headers: Vec<(String, String)> = enabled(request.get("header"))
+        .into_iter()
+        .map(|(key, value)| (key, variables(&value)))
+        .collect();
+
+    match request.get("auth").or(auth).and_then(self::auth) {
+        Some(Auth::Header(key, value)) => headers.push((key, value)),
+        Some(Auth::Query(key, value)) => {
+            let separator: char = if url.contains('?') { '&' } else { '?' };
+            url = format!("{url}{separator}{key}={value}");
+        }
+        None => {}
+    }
+
+    let mut file = RequestFile::new(method, &url);
+    for (key, value) in headers {
+        file.header(&key, &value);
+    }
+
+    if let Some((body, content_type)) = request.get("body").and_then(body) {
+        if let Some(content_type) = content_type.filter(|_| !file.has_header("content-type")) {
+            file.header("content-type", content_type);
+        }
+        file.body(&variables(&body));
+    }
+
+    file
+}
+
+fn url(url: &Value) -> String {
+    if let Some(raw) = url.as_str().or_else(|| url.get("raw").and_then(Value::as_str)) {
+        return variables(raw);
+    }
+
+    let join = |key: &str, separator: &str| -> String {
+        match url.get(key) {
+            Some(Value::Array(parts)) => {
+                parts.iter().filter_map(Value::as_str).collect::<Vec<&str>>().join(separator)
+            }
+            Some(Value::String(part)) => part.clone(),
+            _ => String::new(),
+        }
+    };
+
+    // A host which is a variable, like `{{baseUrl}}`, usually includes the protocol
+    let host: String = join("host", ".");
+    let protocol: String = match url.get("protocol").and_then(Value::as_str) {
+        Some(protocol) => format!("{protocol}://"),
+        None if host.starts_with("{{") => String::new(),
+        None => String::from("https://"),
+    };
+    let mut raw: String = format!("{protocol}{host}/{}", join("path", "/"));
+    let query: Vec<String> = enabled(url.get("query"))
+        .into_iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+
+    if !query.is_empty() {
+        raw = format!("{raw}?{}", query.join("&"));
+    }
+
+    variables(&raw)
+}
+
+enum Auth {
+    Header(String, String),
+    Query(String, String),
+}
+
+fn auth(auth: &Value) -> Option<Auth> {
+    let kind: &str = auth.get("type").and_then(Value::as_str)?;
+    // Authentication parameters are given as a list of key/value pairs, under the name of the type
+    let param = |key: &str| -> Option<String> {
+        auth.get(kind)?
+            .as_array()?
+            .iter()
+            .find(|param| param.get("key").and_then(Value::as_str) == Some(key))
+            .and_then(|param| param.get("value"))
+            .and_then(Value::as_str)
+            .map(variables)
+    };
+
+    match kind {
+        "noauth" => None,
+        "bearer" => Some(Auth::Header(
+            String::from("authorization"),
+            format!("Bearer {}", param("token").unwrap_or_default()),
+        )),
+        "basic" => {
+            let credentials: String = format!(
+                "{}:{}",
+                param("username").unwrap_or_default(),
+                param("password").unwrap_or_default()
+            );
+            if VAR_REGEX.is_match(&credentials) {
+                log::warn!("Basic authentication with template variables is not supported, it must be added manually");
+                None
+            } else {
+                let encoded: String = base64::engine::general_purpose::STANDARD.encode(credentials);
+                Some(Auth::Header(String::from("authorization"), format!("Basic {encoded}")))
+            }
+        }
+        "apikey" => {
+            let key: String = param("key").unwrap_or_else(|| String::from("x-api-key"));
+            let value: String = param("value").unwrap_or_default();
+            match param("in").as_deref() {
+                Some("query") => Some(Auth::Query(key, value)),
+                _ => Some(Auth::Header(key, value)),
+            }
+        }
+        other => {
+            log::warn!(
+                "Authentication of type '{other}' is not supported, it must be added manually"
+            );
+            None
+        }
+    }
+}
+
+/// The body of a request and its content type, if the content type can be determined by the mode
+/// of the body
+fn body(body: &Value) -> Option<(String, Option<&'static str>)> {
+    let mode: &str = body.get("mode").and_then(Value::as_str)?;
+    match mode {
+        "raw" => {
+            let raw: String = body.get("raw").and_then(Value::as_str)?.to_string();
+            let language: Option<&str> =
+                body.pointer("/options/raw/language").and_then(Value::as_str);
+            let content_type: Option<&str> = match language {
+                Some("json") => Some("application/json"),
+                Some("xml") => Some("application/xml"),
+                Some("html") => Some("text/html"),
+                Some("text") => Some("text/plain"),
+                _ => None,
+            };
+            Some((raw, content_type))
+        }
+        "urlencoded" => {
+            let params: String = enabled(body.get("urlencoded"))
+                .iter()
+                .map(|(key, value)| format!("{}={}", form_encode(key), form_encode(value)))
+                .collect::<Vec<String>>()
+                .join("&");
+            Some((params, Some("application/x-www-form-urlencoded")))
+        }
+        "graphql" => {
+            let query: &str = body.pointer("/graphql/query").and_then(Value::as_str)?;
+            let variables: Value = body
+                .pointer("/graphql/variables")
+                .and_then(Value::as_str)
+                .and_then(|variables| serde_json::from_str(variables).ok())
+                .unwrap_or(Value::Null);
+            let graphql = serde_json::json!({ "query": query, "variables": variables });
+            Some((graphql.to_string(), Some("application/json")))
+        }
+        other => {
+            log::warn!("Body of mode '{other}' is not supported, it must be added manually");
+            None
+        }
+    }
+}
+
+/// Encode a key or value of a form, except for variables, which are substituted when the request
+/// is sent
+fn form_encode(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut end: usize = 0;
+    for var in VAR_REGEX.find_iter(input) {
+        out.extend(url::form_urlencoded::byte_serialize(&input.as_bytes()[end..var.start()]));
+        out.push_str(var.as_str());
+        end = var.end();
+    }
+    out.extend(url::form_urlencoded::byte_serialize(&input.as_bytes()[end..]));
+    out
+}
+
+/// Key/value pairs from a list of Postman key/value objects, excluding those that are disabled
+fn enabled(list: Option<&Value>) -> Vec<(String, String)> {
+    let list: &[Value] = match list.and_then(Value::as_array) {
+        Some(list) => list,
+        None => return Vec::new(),
+    };
+
+    list.iter()
+        .filter(|entry| entry.get("disabled").and_then(Value::as_bool) != Some(true))
+        .filter(|entry| entry.get("enabled").and_then(Value::as_bool) != Some(false))
+        .filter_map(|entry| {
+            let key: &str = entry.get("key").and_then(Value::as_str)?;
+            let value: String = match entry.get("value") {
+                Some(Value::String(value)) => value.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(value) => value.to_string(),
+            };
+            Some((key.to_string(), value))
+        })
+        .collect()
+}
+
+/// Content of an environments file with the variables from a list of Postman variables
+fn env_file<F>(variables: Option<&Value>, include: F) -> String
+where
+    F: Fn(&Value) -> bool,
+{
+    let variables: Vec<Value> = match variables.and_then(Value::as_array) {
+        Some(variables) => variables.iter().filter(|v| include(v)).cloned().collect(),
+        None => return String::new(),
+    };
+
+    enabled(Some(&Value::Array(variables)))
+        .into_iter()
+        .map(|(key, value)| format!("{}={}\n", variable_name(&key), value))
+        .collect()
+}
+
+/// Postman variables are mostly compatible with Handlebars, but names that contains characters
+/// that are not valid in a template key are normalized, the same way as in environments files
+fn variables(input: &str) -> String {
+    VAR_REGEX
+        .replace_all(input, |caps: &Captures| {
+            let name: &str = &caps[1];
+            if name.starts_with('$') {
+                log::warn!("Postman dynamic variable {{{{{name}}}}} is not supported");
+            }
+            format!("{{{{{}}}}}", variable_name(name))
+        })
+        .to_string()
+}
+
+fn variable_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// A name that can be used as a file or directory name
+fn file_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect::<String>()
+        .trim_matches(|c| c == '_' || c == '.')
+        .to_string()
+}
+
+/// Make `name` unique among `names`, by adding a numbered suffix if needed
+fn unique(name: String, names: &mut HashSet<String>) -> String {
+    let mut candidate: String = name.clone();
+    let mut n: usize = 2;
+    while names.contains(&candidate) {
+        candidate = format!("{name}_{n}");
+        n += 1;
+    }
+    names.insert(candidate.clone());
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{items, PostmanFile};
+
+    #[test]
+    fn test_collection_with_folders_and_inherited_auth() {
+        let collection = json!({
+            "info": { "name": "Users", "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json" },
+            "auth": { "type": "bearer", "bearer": [{ "key": "token", "value": "{{access token}}" }] },
+            "item": [{
+                "name": "Admin users",
+                "item": [{
+                    "name": "Create user",
+                    "request": {
+                        "method": "POST",
+                        "header": [
+                            { "key": "Accept", "value": "application/json" },
+                            { "key": "X-Debug", "value": "1", "disabled": true }
+                        ],
+                        "url": { "raw": "{{baseUrl}}/users", "host": ["{{baseUrl}}"], "path": ["users"] },
+                        "body": { "mode": "raw", "raw": "{\"name\":\"{{name}}\"}", "options": { "raw": { "language": "json" } } }
+                    }
+                }]
+            }]
+        });
+
+        let mut files: Vec<PostmanFile> = Vec::new();
+        items(&collection, std::path::Path::new(""), collection.get("auth"), &mut files);
+
+        let (path, request) = match &files[0] {
+            PostmanFile::Request(path, request) => (path, request),
+            PostmanFile::Environment(..) => panic!("Expected a request"),
+        };
+
+        let expected = r#"method: POST
+url: '{{baseUrl}}/users'
+headers:
+  accept: application/json
+  authorization: Bearer {{access_token}}
+  content-type: application/json
+body: '{"name":"{{name}}"}'
+"#;
+
+        assert_eq!(std::path::PathBuf::from("Admin_users/Create_user.yml"), *path);
+        assert_eq!(expected, request.to_yaml());
+    }
+
+    #[test]
+    fn test_urlencoded_body_with_variables() {
+        let body = json!({
+            "mode": "urlencoded",
+            "urlencoded": [
+                { "key": "username", "value": "{{user name}}" },
+                { "key": "note", "value": "a&b={{note}} c" }
+            ]
+        });
+
+        let (body, content_type) = super::body(&body).unwrap();
+        assert_eq!("username={{user name}}&note=a%26b%3D{{note}}+c", body);
+        assert_eq!("username={{user_name}}&note=a%26b%3D{{note}}+c", super::variables(&body));
+        assert_eq!(Some("application/x-www-form-urlencoded"), content_type);
+    }
+}
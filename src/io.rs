@@ -1,37 +1,116 @@
-use std::io::Write;
-use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
+use std::io::{IsTerminal, Write};
+use termcolor::{Buffer, BufferWriter, Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
-pub fn write(stream: &mut StandardStream, content: &str) {
+use crate::args::Args;
+use crate::output::Format;
+use crate::pager::{self, Paging};
+
+/// Streams that output is written to. The body of a response is written to stdout, while
+/// decorations such as titles, status lines, borders and headers, and messages such as the outcome
+/// of a snapshot comparison, are written to stderr when stdout is not a terminal or the output is
+/// JSON. This way only the body ends up in a file or a pipe.
+pub struct Output {
+    pub body: StandardStream,
+    pub messages: StandardStream,
+    decorations: bool,
+    colors: ColorChoice,
+    paging: Paging,
+    pager: Option<String>,
+}
+
+impl Output {
+    pub fn new(args: &Args) -> Output {
+        let terminal: bool = std::io::stdout().is_terminal();
+        // With JSON output, stdout must only contain JSON, so messages always go to stderr
+        let messages: StandardStream = match (terminal, args.format()) {
+            (true, Format::Terminal) => StandardStream::stdout(args.use_colors()),
+            _ => StandardStream::stderr(args.stderr_colors()),
+        };
+
+        Output {
+            body: StandardStream::stdout(args.use_colors()),
+            messages,
+            decorations: args.decorations(),
+            colors: args.use_colors(),
+            paging: if terminal { args.paging() } else { Paging::Never },
+            pager: args.pager_command(),
+        }
+    }
+
+    /// Stream for decorations, or `None` if only the body should be printed
+    pub fn decorations(&mut self) -> Option<&mut StandardStream> {
+        match self.decorations {
+            true => Some(&mut self.messages),
+            false => None,
+        }
+    }
+
+    /// Buffer to write output to when it may be shown in a pager, which is when stdout is a
+    /// terminal. Decorations are then written to the same buffer as the body, since both would
+    /// have been written to stdout.
+    pub fn pageable(&self) -> Option<Buffer> {
+        match self.paging {
+            Paging::Never => None,
+            Paging::Auto | Paging::Always => Some(self.buffer()),
+        }
+    }
+
+    /// Buffer for output to stdout, which is printed with [`Output::page`]
+    pub fn buffer(&self) -> Buffer {
+        BufferWriter::stdout(self.colors).buffer()
+    }
+
+    /// Show buffered output in a pager if it is taller than the terminal, or print it as usual
+    pub fn page(&mut self, buffer: Buffer) {
+        let height: Option<usize> =
+            dialoguer::console::Term::stdout().size_checked().map(|(rows, _)| rows as usize);
+        let lines: usize = buffer.as_slice().iter().filter(|b| **b == b'\n').count();
+        let page: bool = match self.paging {
+            Paging::Always => true,
+            Paging::Auto => height.map(|height| lines >= height).unwrap_or(false),
+            Paging::Never => false,
+        };
+
+        if !page || !pager::page(buffer.as_slice(), self.pager.clone()) {
+            self.body.write_all(buffer.as_slice()).unwrap();
+        }
+    }
+}
+
+pub fn write<W: WriteColor>(stream: &mut W, content: &str) {
     stream.set_color(ColorSpec::new().set_fg(None)).unwrap();
     write!(stream, "{content}").unwrap();
 }
 
-pub fn writeln(stream: &mut StandardStream, content: &str) {
+pub fn writeln<W: WriteColor>(stream: &mut W, content: &str) {
     stream.set_color(ColorSpec::new().set_fg(None)).unwrap();
     writeln!(stream, "{content}").unwrap();
 }
 
-pub fn write_color(stream: &mut StandardStream, content: &str, color: Option<Color>) {
+pub fn write_color<W: WriteColor>(stream: &mut W, content: &str, color: Option<Color>) {
     stream.set_color(ColorSpec::new().set_fg(color)).unwrap();
     write!(stream, "{content}").unwrap();
 }
 
-pub fn writeln_color(stream: &mut StandardStream, content: &str, color: Option<Color>) {
+#[allow(dead_code)]
+pub fn writeln_color<W: WriteColor>(stream: &mut W, content: &str, color: Option<Color>) {
     stream.set_color(ColorSpec::new().set_fg(color)).unwrap();
     writeln!(stream, "{content}").unwrap();
 }
 
-pub fn write_spec(stream: &mut StandardStream, content: &str, spec: &ColorSpec) {
+#[allow(dead_code)]
+pub fn write_spec<W: WriteColor>(stream: &mut W, content: &str, spec: &ColorSpec) {
     stream.set_color(spec).unwrap();
     write!(stream, "{content}").unwrap();
 }
 
-pub fn writeln_spec(stream: &mut StandardStream, content: &str, spec: &ColorSpec) {
+pub fn writeln_spec<W: WriteColor>(stream: &mut W, content: &str, spec: &ColorSpec) {
     stream.set_color(spec).unwrap();
     writeln!(stream, "{content}").unwrap();
 }
 
-pub fn write_body(stream: &mut StandardStream, content_type: Option<&str>, body: String) {
+#[allow(dead_code)]
+pub fn write_body<W: WriteColor>(stream: &mut W, content_type: Option<&str>, body: String) {
     let body: String = match content_type {
         Some(content_type) => {
             if content_type.starts_with("application/json") {
@@ -46,3 +125,11 @@ pub fn write_body(stream: &mut StandardStream, content_type: Option<&str>, body:
     writeln(stream, &format!("\n{body}"));
 }
 
+pub fn status_color(status: u16) -> Option<Color> {
+    match status {
+        200..=299 => Some(Color::Green),
+        400..=499 => Some(Color::Yellow),
+        500..=599 => Some(Color::Red),
+        _ => None,
+    }
+}
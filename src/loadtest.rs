@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use httpx::grpc::{Call, GrpcError};
+use httpx::request::Client;
+use httpx::{HttpRequest, HttpResponse, TransportError};
+use serde_json::{json, Map, Value};
+use termcolor::StandardStream;
+
+use crate::error::FireError;
+use crate::io::{status_color, write, write_color, writeln};
+use crate::request::Request;
+
+const PERCENTILES: [usize; 3] = [50, 90, 99];
+
+/// How many times, and for how long, a request is executed in a load test
+pub struct Options {
+    pub repeat: Option<usize>,
+    pub concurrency: usize,
+    pub duration: Option<Duration>,
+}
+
+/// Outcome of a single request, which is either the status of the response or the kind of error
+/// that prevented the request from completing
+type Outcome = Result<u16, &'static str>;
+
+pub struct Report {
+    elapsed: Duration,
+    outcomes: Vec<(Duration, Outcome)>,
+}
+
+/// A request that is prepared once and then sent in every iteration, so that connections are
+/// reused and a gRPC message is not compiled and encoded again for each request
+enum Sender {
+    Http(Client, HttpRequest),
+    Grpc(Box<Call>),
+}
+
+/// Execute a request repeatedly from `concurrency` threads, until the request has been executed
+/// `repeat` times or `duration` has passed, whichever comes first
+pub fn run(request: &Request, timeout: Duration, options: &Options) -> Result<Report, FireError> {
+    let sender: Sender = match request {
+        Request::Http(request) => Sender::Http(Client::new(options.concurrency), request.clone()),
+        Request::Grpc(request) => Sender::Grpc(Box::new(Call::new(request.clone())?)),
+    };
+    let limit: usize = options.repeat.unwrap_or(usize::MAX);
+    let issued = AtomicUsize::new(0);
+    let start: Instant = Instant::now();
+    let deadline: Option<Instant> = options.duration.map(|duration| start + duration);
+
+    let outcomes: Vec<(Duration, Outcome)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..options.concurrency.max(1))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut outcomes: Vec<(Duration, Outcome)> = Vec::new();
+                    while deadline.map(|d| Instant::now() < d).unwrap_or(true)
+                        && issued.fetch_add(1, Ordering::Relaxed) < limit
+                    {
+                        let sent: Instant = Instant::now();
+                        let outcome: Outcome = sender.send(timeout).map(|r| r.status());
+                        outcomes.push((sent.elapsed(), outcome));
+                    }
+                    outcomes
+                })
+            })
+            .collect();
+
+        workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+    });
+
+    Ok(Report {
+        elapsed: start.elapsed(),
+        outcomes,
+    })
+}
+
+impl Sender {
+    fn send(&self, timeout: Duration) -> Result<HttpResponse, &'static str> {
+        match self {
+            Sender::Http(client, request) => {
+                client.call(request.clone(), timeout).map_err(|e| error_kind(&e))
+            }
+            Sender::Grpc(call) => call.send(timeout).map_err(|e| match e {
+                GrpcError::Transport(e) => error_kind(&e),
+                _ => "grpc",
+            }),
+        }
+    }
+}
+
+fn error_kind(error: &TransportError) -> &'static str {
+    match error {
+        TransportError::Timeout(..) => "timeout",
+        TransportError::Connection(_) => "connection",
+        TransportError::UnknownHost(_) => "unknown host",
+        TransportError::Other(_) => "other",
+    }
+}
+
+impl Report {
+    pub fn print(&self, stdout: &mut StandardStream) {
+        let requests: usize = self.outcomes.len();
+        let seconds: f64 = self.elapsed.as_secs_f64();
+        writeln(
+            stdout,
+            &format!("Requests  {requests} in {seconds:.2} s ({:.1} req/s)", self.throughput()),
+        );
+
+        let (statuses, errors) = self.counts();
+        if !statuses.is_empty() {
+            write(stdout, "Status   ");
+            for (status, count) in statuses {
+                write(stdout, " ");
+                write_color(stdout, &status.to_string(), status_color(status));
+                write(stdout, &format!(" × {count}"));
+            }
+            writeln(stdout, "");
+        }
+
+        if !errors.is_empty() {
+            write(stdout, "Errors   ");
+            for (kind, count) in errors {
+                write(stdout, " ");
+                write_color(stdout, kind, status_color(500));
+                write(stdout, &format!(" × {count}"));
+            }
+            writeln(stdout, "");
+        }
+
+        let latencies: Vec<Duration> = self.latencies();
+        if let Some(max) = latencies.last() {
+            let percentiles: Vec<String> = PERCENTILES
+                .iter()
+                .map(|p| format!("p{p} {} ms", percentile(&latencies, *p).as_millis()))
+                .collect();
+            writeln(
+                stdout,
+                &format!("Latency   {}  max {} ms", percentiles.join("  "), max.as_millis()),
+            );
+        }
+    }
+
+    /// The report as a single JSON object, for `--format json`
+    pub fn json(&self) -> Value {
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let (statuses, errors) = self.counts();
+        let statuses: Map<String, Value> = statuses
+            .into_iter()
+            .map(|(status, count)| (status.to_string(), count.into()))
+            .collect();
+        let errors: Map<String, Value> = errors
+            .into_iter()
+            .map(|(kind, count)| (kind.to_string(), count.into()))
+            .collect();
+
+        let latencies: Vec<Duration> = self.latencies();
+        let latency: Value = match latencies.last() {
+            Some(max) => {
+                let mut latency: Map<String, Value> = PERCENTILES
+                    .iter()
+                    .map(|p| (format!("p{p}_ms"), millis(percentile(&latencies, *p)).into()))
+                    .collect();
+                latency.insert(String::from("max_ms"), millis(*max).into());
+                Value::Object(latency)
+            }
+            None => Value::Null,
+        };
+
+        json!({
+            "requests": self.outcomes.len(),
+            "duration_ms": millis(self.elapsed),
+            "throughput": self.throughput(),
+            "statuses": statuses,
+            "errors": errors,
+            "latency": latency,
+        })
+    }
+
+    /// Requests per second
+    fn throughput(&self) -> f64 {
+        let seconds: f64 = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.outcomes.len() as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// Number of responses with each status, and number of errors of each kind
+    fn counts(&self) -> (BTreeMap<u16, usize>, BTreeMap<&str, usize>) {
+        let mut statuses: BTreeMap<u16, usize> = BTreeMap::new();
+        let mut errors: BTreeMap<&str, usize> = BTreeMap::new();
+        for (_, outcome) in &self.outcomes {
+            match outcome {
+                Ok(status) => *statuses.entry(*status).or_default() += 1,
+                Err(kind) => *errors.entry(kind).or_default() += 1,
+            }
+        }
+        (statuses, errors)
+    }
+
+    /// Sorted latencies. Latency is only measured for requests that got a response.
+    fn latencies(&self) -> Vec<Duration> {
+        let mut latencies: Vec<Duration> = self
+            .outcomes
+            .iter()
+            .filter(|(_, outcome)| outcome.is_ok())
+            .map(|(latency, _)| *latency)
+            .collect();
+        latencies.sort();
+        latencies
+    }
+}
+
+/// The latency which `percentile` percent of the requests were at or below, using the nearest
+/// rank method. The latencies must be sorted.
+fn percentile(latencies: &[Duration], percentile: usize) -> Duration {
+    if latencies.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank: usize = (percentile * latencies.len()).div_ceil(100);
+    latencies[rank.clamp(1, latencies.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{percentile, Report};
+
+    #[test]
+    fn test_percentile() {
+        let latencies: Vec<Duration> = (1..=200).map(Duration::from_millis).collect();
+        assert_eq!(Duration::from_millis(100), percentile(&latencies, 50));
+        assert_eq!(Duration::from_millis(180), percentile(&latencies, 90));
+        assert_eq!(Duration::from_millis(198), percentile(&latencies, 99));
+        assert_eq!(Duration::from_millis(200), percentile(&latencies, 100));
+        assert_eq!(Duration::from_millis(7), percentile(&latencies[6..7], 50));
+        assert_eq!(Duration::ZERO, percentile(&[], 50));
+    }
+
+    #[test]
+    fn test_json_report() {
+        let report = Report {
+            elapsed: Duration::from_secs(2),
+            outcomes: vec![
+                (Duration::from_millis(10), Ok(200)),
+                (Duration::from_millis(30), Ok(200)),
+                (Duration::from_millis(20), Ok(503)),
+                (Duration::from_secs(5), Err("timeout")),
+            ],
+        };
+
+        let json = serde_json::json!({
+            "requests": 4,
+            "duration_ms": 2000.0,
+            "throughput": 2.0,
+            "statuses": { "200": 2, "503": 1 },
+            "errors": { "timeout": 1 },
+            "latency": { "p50_ms": 20.0, "p90_ms": 30.0, "p99_ms": 30.0, "max_ms": 30.0 },
+        });
+        assert_eq!(json, report.json());
+    }
+}
@@ -1,33 +1,58 @@
 mod args;
+mod config;
 mod dbg;
+mod defaults;
 mod error;
+mod export;
+mod extends;
 mod format;
+mod har;
+mod helpers;
+mod history;
+mod http_file;
+mod import;
 mod io;
+mod loadtest;
 mod logger;
+mod output;
+mod pager;
 mod prop;
+mod query;
+mod request;
+mod snapshot;
 mod templ;
 mod template;
+mod theme;
 
-use crate::args::Args;
+use crate::args::{Args, Command, History};
 use crate::dbg::dbg_info;
+use crate::defaults::Defaults;
 use crate::error::exit;
 use crate::format::ContentFormatter;
+use crate::http_file::HttpFile;
+use crate::io::status_color;
 use crate::io::write;
 use crate::io::write_color;
 use crate::io::writeln;
 use crate::io::writeln_spec;
+use crate::io::Output;
 use crate::logger::setup_logging;
+use crate::output::Format;
 use crate::prop::Property;
-use crate::template::substitution;
+use crate::request::Request;
+use crate::template::{substitution, yaml_substitution};
+use chrono::{DateTime, Local, Utc};
 use clap::Parser;
 use error::FireError;
-use httpx::HttpRequest;
+use http::HeaderMap;
+use httpx::grpc::GrpcError;
+use std::path::PathBuf;
 use std::process::ExitCode;
-use std::str::FromStr;
 use std::time::Duration;
 use std::time::Instant;
-use template::SubstitutionError;
-use termcolor::{Color, ColorSpec, StandardStream};
+use syntect::highlighting::Theme;
+use template::{MissingKeys, SubstitutionError};
+use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
 
 fn main() -> ExitCode {
     match exec() {
@@ -37,17 +62,30 @@ fn main() -> ExitCode {
 }
 
 fn exec() -> Result<(), FireError> {
-    let args: Args = Args::parse();
-    setup_logging(args.verbosity_level);
+    let mut args: Args = Args::parse();
+    args.load_config()?;
+    setup_logging(args.verbosity());
     log::debug!("Config: {:?}", args);
 
-    let mut stdout = StandardStream::stdout(args.use_colors());
+    let mut out = Output::new(&args);
 
     if args.print_dbg {
-        write(&mut stdout, &dbg_info());
+        write(&mut out.body, &dbg_info());
         return Ok(());
     }
 
+    if let Some(command) = &args.command {
+        return match command {
+            Command::Import(import) => import::import(import),
+            Command::History {
+                command,
+                search,
+                limit,
+            } => show_history(&args, &mut out, command.as_ref(), search.as_deref(), *limit),
+            Command::Themes => show_themes(&args, &mut out),
+        };
+    }
+
     // Read file content
     let file = match std::fs::read_to_string(args.file()) {
         Ok(file) => file,
@@ -59,67 +97,353 @@ fn exec() -> Result<(), FireError> {
                 std::io::ErrorKind::PermissionDenied => {
                     Err(FireError::NoReadPermission(args.file().to_path_buf()))
                 }
+                std::io::ErrorKind::IsADirectory => {
+                    Err(FireError::NotAFile(args.file().to_path_buf()))
+                }
                 _ => Err(FireError::GenericIO(e.to_string())),
             }
         }
     };
 
     // Read enviroment variables from system environment and extra environments supplied via cli
-    let props: Vec<Property> = match args.env() {
+    let mut props: Vec<Property> = match args.env() {
         Ok(env) => env,
         Err(err) => return Err(FireError::Environment(err)),
     };
+
+    // A `.http` file may contain several requests, and declarations of variables
+    let file: String = if http_file::is_http_file(args.file()) {
+        let http_file = HttpFile::parse(&file);
+        let content: &str = http_file.select(args.name())?;
+        let declared: Vec<Property> = http_file
+            .variables(content, &props, args.interactive(), args.try_colors(), args.trim)
+            .map_err(|e| substitution_error(&args, e))?;
+        props.extend(declared);
+        content.to_string()
+    } else {
+        file
+    };
     log::debug!("Received properties {:?}", props);
 
-    // Apply template substitution
-    let content: String =
-        substitution(file, props, args.interactive(), args.try_colors(), args.trim)?;
+    // Apply template substitution, where YAML files are rendered one string scalar at a time
+    let render = |content: String| -> Result<String, FireError> {
+        yaml_substitution(content, props.clone(), args.interactive(), args.try_colors(), args.trim)
+            .map_err(|e| substitution_error(&args, e))
+    };
+
+    // Merge with request files that the request file extends, if any
+    let content: String = match http_file::is_http_file(args.file()) {
+        true => substitution(file, props.clone(), args.interactive(), args.try_colors(), args.trim)
+            .map_err(|e| substitution_error(&args, e))?,
+        false => extends::resolve(render(file)?, args.file(), render)?,
+    };
+
+    // Defaults from `_defaults.yml` files, which are templates too
+    let defaults: Defaults = defaults::load(args.file(), render)?;
+
+    let formatters: Vec<Box<dyn ContentFormatter>> = formatters(&args)?;
 
     // Parse Validate format of request
-    let mut request: HttpRequest = HttpRequest::from_str(&content).unwrap();
+    let request: Request = Request::parse(&content, args.file(), &defaults)?;
+
+    if let Some(export) = args.export() {
+        return match &request {
+            Request::Http(request) => {
+                println!("{}", export::command(request, export)?);
+                Ok(())
+            }
+            Request::Grpc(_) => {
+                Err(FireError::Other(String::from("gRPC requests cannot be exported as a command")))
+            }
+        };
+    }
+
+    let file: PathBuf = args.file().canonicalize().unwrap_or_else(|_| args.file().to_path_buf());
+    let origin = history::Origin {
+        file: &file,
+        content: &content,
+        environments: args.environments(),
+        defaults: &defaults,
+    };
+
+    execute(&args, &mut out, &formatters, &request, &origin)
+}
+
+/// Formatters for bodies, where no formatting is done for raw output
+fn formatters(args: &Args) -> Result<Vec<Box<dyn ContentFormatter>>, FireError> {
+    if args.raw() {
+        return Ok(Vec::new());
+    }
+    let theme: Option<Theme> = match args.try_colors() {
+        true => Some(theme::load(args.theme().as_deref())?),
+        false => None,
+    };
+    Ok(format::formatters(theme))
+}
+
+/// Print a sample JSON body highlighted with each available theme
+fn show_themes(args: &Args, out: &mut Output) -> Result<(), FireError> {
+    const SAMPLE: &str =
+        r#"{"id":42,"name":"fire","tags":["http","cli"],"active":true,"parent":null}"#;
+
+    let current: String = args.theme().unwrap_or_else(|| String::from(theme::DEFAULT));
+    let mut spec = ColorSpec::new();
+    spec.set_bold(true);
+
+    let mut buffer: termcolor::Buffer = out.buffer();
+    for (name, theme) in theme::themes().themes {
+        let title: String = match name == current {
+            true => format!("{name} (current)"),
+            false => name,
+        };
+        let formatters: Vec<Box<dyn ContentFormatter>> = format::formatters(Some(theme));
+        let sample: String = format::format(&formatters, Some("application/json"), SAMPLE);
+        writeln_spec(&mut buffer, &title, &spec);
+        writeln(&mut buffer, &format!("{sample}\n"));
+    }
 
-    // Add default header, if missing
-    request.set_default_headers().unwrap();
+    out.page(buffer);
+    Ok(())
+}
 
-    // Print request (optional)
-    let syntax_hilighiting: bool = args.try_colors();
-    let formatters: Vec<Box<dyn ContentFormatter>> = format::formatters(syntax_hilighiting);
+/// Execute a request and print its response, optionally saving the exchange to a HAR file and to
+/// the history
+fn execute(
+    args: &Args,
+    out: &mut Output,
+    formatters: &[Box<dyn ContentFormatter>],
+    request: &Request,
+    origin: &history::Origin,
+) -> Result<(), FireError> {
+    let body: Option<String> = request.body()?;
+    let headers: HeaderMap = request.headers();
+    let title: String = request.title();
+
+    if let (true, Format::Terminal) = (args.print_request(), args.format()) {
+        if let Some(stream) = out.decorations() {
+            let content_type: Option<&str> = request.content_type();
+            print_request(
+                stream,
+                args,
+                formatters,
+                &title,
+                &headers,
+                body.as_deref(),
+                content_type,
+            );
+        }
+    }
 
-    let req_headers = request.headers();
+    if !confirm(args) {
+        log::debug!("Request cancelled by user");
+        return Ok(());
+    }
 
-    let content_type: Option<&str> = request.header("content-type");
+    // Execute request repeatedly and only report statistics, when load testing
+    if let Some(options) = args.load_test() {
+        let report: loadtest::Report =
+            loadtest::run(request, args.timeout(origin.defaults.timeout), &options)?;
+        match args.format() {
+            Format::Terminal => report.print(&mut out.body),
+            Format::Json => println!("{}", serde_json::to_string_pretty(&report.json()).unwrap()),
+        }
+        return Ok(());
+    }
 
-    if args.print_request() {
-        let title: String = format!("{} {}", request.method(), request.url().unwrap());
-        writeln(&mut stdout, &title);
-        let border = "━".repeat(title.len());
-        writeln(&mut stdout, &border);
+    // Make request
+    let started: DateTime<Utc> = Utc::now();
+    let start: Instant = Instant::now();
+    let response: httpx::HttpResponse =
+        request.call(args.timeout(origin.defaults.timeout), args.timings())?;
+    let end: Instant = Instant::now();
+    let duration: Duration = end.duration_since(start);
 
-        if args.print_headers() {
-            let mut spec = ColorSpec::new();
-            spec.set_dimmed(true);
-            for (k, v) in &req_headers {
-                let value: &str = v.to_str().unwrap_or("**Invalid header value**");
-                writeln_spec(&mut stdout, &format!("{}: {}", k.as_str(), value), &spec);
+    let exchange = har::Exchange {
+        method: &request.method(),
+        url: &request.url(),
+        headers: &headers,
+        body: body.as_deref(),
+        response: &response,
+        started,
+        duration,
+    };
+
+    let content_type: Option<&str> = request.response_content_type(&response);
+
+    // Filter JSON body of response (optional). If the query fails, the response is still printed
+    // and saved, but without a body, and the error is returned once the exchange is saved.
+    let (response_body, query_error): (String, Option<FireError>) = match args.query() {
+        Some(query) => match query::filter(query, response.body()) {
+            Ok(filtered) => (filtered, None),
+            Err(err) => (String::new(), Some(err)),
+        },
+        None => (response.body().to_string(), None),
+    };
+
+    match args.format() {
+        Format::Terminal => {
+            print_response(out, args, formatters, &response, &response_body, duration, content_type)
+        }
+        Format::Json => {
+            let request_content_type: Option<&str> = request.content_type();
+            let mut json =
+                output::json(&exchange, request_content_type, content_type, origin.environments);
+            if args.query().is_some() {
+                json["response"]["body"] = serde_json::from_str(&response_body).unwrap_or_default();
             }
-            if request.body().is_some() {
-                writeln(&mut stdout, "");
+            println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        }
+    }
+
+    // Save exchange to HAR log (optional)
+    if let Some(path) = args.har() {
+        har::append(path, &exchange)?;
+    }
+
+    if args.save_history() {
+        let entry = history::Entry::new(
+            origin,
+            history::Request {
+                title,
+                headers: history::header_pairs(&headers),
+                body,
+                content_type: request.content_type().map(String::from),
+            },
+            history::Response {
+                version: response.version().to_string(),
+                status: response.status(),
+                headers: history::header_pairs(response.headers()),
+                body: response.body().to_string(),
+                content_type: content_type.map(String::from),
+            },
+            started,
+            duration,
+        );
+
+        // Failing to save the history should not fail an otherwise successful request
+        match history::dir().and_then(|dir| history::record(&dir, entry)) {
+            Ok(id) => log::info!("Saved exchange to history with id {id}"),
+            Err(e) => log::warn!("Unable to save exchange to history: {e}"),
+        }
+    }
+
+    if let Some(err) = query_error {
+        return Err(err);
+    }
+
+    // Compare response with snapshot (optional)
+    if let Some(options) = args.snapshot() {
+        let path: PathBuf = snapshot::path(origin.file, args.name());
+        snapshot::verify(&mut out.messages, &path, &response, &options)?;
+    }
+
+    Ok(())
+}
+
+fn show_history(
+    args: &Args,
+    out: &mut Output,
+    command: Option<&History>,
+    search: Option<&str>,
+    limit: usize,
+) -> Result<(), FireError> {
+    let formatters: Vec<Box<dyn ContentFormatter>> = formatters(args)?;
+
+    match command {
+        None => {
+            let entries: Vec<history::Entry> = history::entries(&history::dir()?)?
+                .into_iter()
+                .filter(|entry| search.map(|search| entry.matches(search)).unwrap_or(true))
+                .collect();
+
+            let skip: usize = entries.len().saturating_sub(limit);
+            for entry in entries.iter().skip(skip) {
+                let started: String = DateTime::parse_from_rfc3339(&entry.started)
+                    .map(|time| time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|_| entry.started.clone());
+
+                let stdout: &mut StandardStream = &mut out.body;
+                write(stdout, &format!("{:>4}  {started}  ", entry.id));
+                let status: u16 = entry.response.status;
+                write_color(stdout, &status.to_string(), status_color(status));
+                writeln(stdout, &format!(" {:>6} ms  {}", entry.duration_ms, entry.request.title));
             }
+            Ok(())
         }
+        Some(History::Show { id }) => {
+            let entry: history::Entry = history::find(&history::dir()?, *id)?;
+            let request: &history::Request = &entry.request;
+            let headers: HeaderMap = history::header_map(&request.headers);
+            let content_type: Option<&str> = request.content_type.as_deref();
+            if let Some(stream) = out.decorations() {
+                print_request(
+                    stream,
+                    args,
+                    &formatters,
+                    &request.title,
+                    &headers,
+                    request.body.as_deref(),
+                    content_type,
+                );
+            }
 
-        if let Some(body) = request.body() {
-            let content: String = formatters
-                .iter()
-                .filter(|fmt| fmt.accept(content_type))
-                .fold(body.clone(), |content, fmt| fmt.format(content).unwrap());
+            let content_type: Option<&str> = entry.response.content_type.as_deref();
+            let response: httpx::HttpResponse = entry.response();
+            print_response(
+                out,
+                args,
+                &formatters,
+                &response,
+                response.body(),
+                entry.duration(),
+                content_type,
+            );
+            Ok(())
+        }
+        Some(History::Replay { id }) => {
+            let entry: history::Entry = history::find(&history::dir()?, *id)?;
+            let request: Request = Request::parse(&entry.content, &entry.file, &entry.defaults)?;
+            execute(args, out, &formatters, &request, &entry.origin())
+        }
+    }
+}
 
-            writeln(&mut stdout, &content);
+fn print_request(
+    stdout: &mut StandardStream,
+    args: &Args,
+    formatters: &[Box<dyn ContentFormatter>],
+    title: &str,
+    headers: &HeaderMap,
+    body: Option<&str>,
+    content_type: Option<&str>,
+) {
+    writeln(stdout, title);
+    let border = "━".repeat(title.len());
+    writeln(stdout, &border);
+
+    if args.print_headers() {
+        let mut spec = ColorSpec::new();
+        spec.set_dimmed(true);
+        for (k, v) in headers {
+            let value: &str = v.to_str().unwrap_or("**Invalid header value**");
+            writeln_spec(stdout, &format!("{}: {}", k.as_str(), value), &spec);
+        }
+        if body.is_some() {
+            writeln(stdout, "");
         }
-        writeln(&mut stdout, "");
     }
 
+    if let Some(body) = body {
+        let content: String = format::format(formatters, content_type, body);
+
+        writeln(stdout, &content);
+    }
+    writeln(stdout, "");
+}
+
+fn confirm(args: &Args) -> bool {
     // Ask for confirmation (optional)
-    let fire: bool = if args.ask() {
+    if args.ask() {
         let theme = dialoguer::theme::ColorfulTheme::default();
         let prompt = if args.use_colors() != termcolor::ColorChoice::Never {
             dialoguer::Confirm::with_theme(&theme)
@@ -132,85 +456,143 @@ fn exec() -> Result<(), FireError> {
             .expect("Unrecoverable terminal I/O error")
     } else {
         true
-    };
+    }
+}
 
-    if !fire {
-        log::debug!("Request cancelled by user");
-        return Ok(());
+/// Print the phases of a request as a waterfall, where each bar starts where the previous phase
+/// ended
+fn print_timings(stdout: &mut impl WriteColor, timings: &httpx::Timings) {
+    const WIDTH: f64 = 40.0;
+
+    let phases: [(&str, Option<Duration>); 5] = [
+        ("DNS lookup", timings.dns),
+        ("TCP connect", timings.connect),
+        ("TLS handshake", timings.tls),
+        ("First byte", Some(timings.first_byte)),
+        ("Transfer", Some(timings.transfer)),
+    ];
+
+    let total: f64 = timings.total().as_secs_f64().max(f64::EPSILON);
+    let mut elapsed: f64 = 0.0;
+    for (name, duration) in phases {
+        let duration: f64 = match duration {
+            Some(duration) => duration.as_secs_f64(),
+            None => continue,
+        };
+        let offset: usize = (elapsed / total * WIDTH).round() as usize;
+        let end: usize = ((elapsed + duration) / total * WIDTH).round() as usize;
+        elapsed += duration;
+
+        write(
+            stdout,
+            &format!("{name:<14}{:>7.1} ms  {}", duration * 1000.0, " ".repeat(offset)),
+        );
+        write_color(stdout, &"█".repeat(end.saturating_sub(offset).max(1)), Some(Color::Cyan));
+        writeln(stdout, "");
     }
+    writeln(stdout, "");
+}
 
-    // Make request
-    let start: Instant = Instant::now();
-    let response: httpx::HttpResponse = httpx::request::call(request, args.timeout())?;
-    let end: Instant = Instant::now();
-    let duration: Duration = end.duration_since(start);
+/// Print the response, where the body is written to stdout and everything else is written to the
+/// stream for decorations, if any
+fn print_response(
+    out: &mut Output,
+    args: &Args,
+    formatters: &[Box<dyn ContentFormatter>],
+    response: &httpx::HttpResponse,
+    body: &str,
+    duration: Duration,
+    content_type: Option<&str>,
+) {
+    log::debug!("Body of response:\n{}", response.body());
+
+    let content: String = match body.is_empty() {
+        true => String::new(),
+        false => format::format(formatters, content_type, body),
+    };
 
-    // Handle respone
-    let status: u16 = response.status();
+    // Long responses are shown in a pager, which requires knowing how tall the output is
+    if let Some(mut buffer) = out.pageable() {
+        if args.decorations() {
+            print_status(&mut buffer, args, response, body.is_empty(), duration);
+        }
+        print_body(&mut buffer, args, &content);
+        out.page(buffer);
+        return;
+    }
 
-    let status_color: Option<Color> = match status {
-        200..=299 => Some(Color::Green),
-        400..=499 => Some(Color::Yellow),
-        500..=599 => Some(Color::Red),
-        _ => None,
-    };
+    if let Some(stream) = out.decorations() {
+        print_status(stream, args, response, body.is_empty(), duration);
+    }
+    print_body(&mut out.body, args, &content);
+}
 
-    let body: &str = response.body();
-    log::debug!("Body of response:\n{body}");
+fn print_body(stdout: &mut impl WriteColor, args: &Args, content: &str) {
+    if !content.is_empty() {
+        io::write(stdout, content);
+        if !content.ends_with('\n') && !args.raw() {
+            io::writeln(stdout, "");
+        }
+    }
+}
 
-    let (body_len, unit): (usize, String) = if body.len() >= 1024 {
-        ((body.len() / 1024), String::from("kb"))
+/// Print the status line of a response, followed by timings and headers (optional)
+fn print_status(
+    stdout: &mut impl WriteColor,
+    args: &Args,
+    response: &httpx::HttpResponse,
+    empty_body: bool,
+    duration: Duration,
+) {
+    let (body_len, unit): (usize, String) = if response.body_len() >= 1024 {
+        ((response.body_len() / 1024), String::from("kb"))
     } else {
-        (body.len(), String::from("b"))
+        (response.body_len(), String::from("b"))
     };
 
     let version: String = format!("{} ", response.version());
 
-    write(&mut stdout, &version);
+    write(stdout, &version);
 
-    let status: String = status.to_string();
-    write_color(&mut stdout, &status, status_color);
+    let status: String = response.status().to_string();
+    write_color(stdout, &status, status_color(response.status()));
 
     let outcome: String = format!(" {} ms {} {}", duration.as_millis(), body_len, unit);
-    writeln(&mut stdout, &outcome);
+    writeln(stdout, &outcome);
 
     let border_len: usize = version.len() + status.len() + outcome.len();
     let border = "━".repeat(border_len);
-    writeln(&mut stdout, &border);
+    writeln(stdout, &border);
+
+    if args.timings() {
+        match response.timings() {
+            Some(timings) => print_timings(stdout, timings),
+            None => writeln(stdout, "Timings are not available for this request\n"),
+        }
+    }
 
     if args.print_headers() {
         let mut spec = ColorSpec::new();
         spec.set_dimmed(true);
         for (key, value) in response.headers() {
-            writeln_spec(&mut stdout, &format!("{}: {:?}", key, value), &spec);
+            writeln_spec(stdout, &format!("{}: {:?}", key, value), &spec);
         }
-        if !body.is_empty() {
-            io::writeln(&mut stdout, "");
+        if !empty_body {
+            io::writeln(stdout, "");
         }
     }
-
-    if !body.is_empty() {
-        let content_type = response.header("content-type");
-        let content: String = formatters
-            .iter()
-            .filter(|fmt| fmt.accept(content_type))
-            .fold(body.to_string(), |content, fmt| fmt.format(content).unwrap());
-
-        io::write(&mut stdout, &content);
-        if !content.ends_with('\n') {
-            io::writeln(&mut stdout, "");
-        }
-    }
-
-    Ok(())
 }
 
-impl From<SubstitutionError> for FireError {
-    fn from(e: SubstitutionError) -> Self {
-        match e {
-            SubstitutionError::MissingValue(err) => FireError::TemplateKey(err),
-            SubstitutionError::Rendering => FireError::TemplateRendering,
-        }
+/// Error for a failed template substitution, where missing values are reported along with the
+/// environments files and environments where values were looked for
+fn substitution_error(args: &Args, err: SubstitutionError) -> FireError {
+    match err {
+        SubstitutionError::MissingValues(keys) => FireError::TemplateKey(MissingKeys {
+            keys,
+            env_files: args.env_files(),
+            environments: args.environments().to_vec(),
+        }),
+        SubstitutionError::Rendering(err) => FireError::TemplateRendering(err),
     }
 }
 
@@ -224,3 +606,12 @@ impl From<httpx::TransportError> for FireError {
         }
     }
 }
+
+impl From<GrpcError> for FireError {
+    fn from(e: GrpcError) -> Self {
+        match e {
+            GrpcError::Transport(err) => err.into(),
+            err => FireError::Grpc(err),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use clap::ValueEnum;
+use http::HeaderMap;
+use serde_json::{json, Map, Value};
+
+use crate::har::Exchange;
+
+/// Format of the output from an executed request
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Decorated output for a terminal, with colors and formatting
+    Terminal,
+    /// The whole exchange as a single JSON object
+    Json,
+}
+
+/// The whole exchange as a single JSON object, where bodies are included as JSON rather than as
+/// text if their content type is JSON
+pub fn json(
+    exchange: &Exchange,
+    request_content_type: Option<&str>,
+    response_content_type: Option<&str>,
+    environments: &[String],
+) -> Value {
+    let response = exchange.response;
+    let mut output: Value = json!({
+        "request": {
+            "method": exchange.method,
+            "url": exchange.url.as_str(),
+            "headers": headers(exchange.headers),
+            "body": exchange.body.map(|body| body_value(body, request_content_type)),
+        },
+        "response": {
+            "version": response.version(),
+            "status": response.status(),
+            "headers": headers(response.headers()),
+            "body": body_value(response.body(), response_content_type),
+        },
+        "started": exchange.started.to_rfc3339(),
+        "duration_ms": exchange.duration.as_secs_f64() * 1000.0,
+        "environments": environments,
+    });
+
+    if let Some(timings) = response.timings() {
+        let millis = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+        output["timings"] = json!({
+            "dns_ms": timings.dns.map(millis),
+            "connect_ms": timings.connect.map(millis),
+            "tls_ms": timings.tls.map(millis),
+            "first_byte_ms": millis(timings.first_byte),
+            "transfer_ms": millis(timings.transfer),
+        });
+    }
+
+    output
+}
+
+/// Headers as a JSON object, where a header with several values has an array of values
+fn headers(headers: &HeaderMap) -> Value {
+    let headers: Map<String, Value> = headers
+        .keys()
+        .map(|key| {
+            let values: Vec<Value> = headers
+                .get_all(key)
+                .iter()
+                .map(|value| Value::from(value.to_str().unwrap_or_default()))
+                .collect();
+            let value: Value = match values.len() {
+                1 => values.into_iter().next().unwrap(),
+                _ => Value::Array(values),
+            };
+            (key.to_string(), value)
+        })
+        .collect();
+
+    Value::Object(headers)
+}
+
+fn body_value(body: &str, content_type: Option<&str>) -> Value {
+    let is_json: bool = content_type.map(|ct| ct.contains("json")).unwrap_or(false);
+    match is_json {
+        true => serde_json::from_str(body).unwrap_or_else(|_| Value::from(body)),
+        false => Value::from(body),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::Utc;
+    use http::{HeaderMap, HeaderValue};
+    use httpx::HttpResponse;
+    use serde_json::json;
+    use url::Url;
+
+    use crate::har::Exchange;
+
+    #[test]
+    fn test_exchange_as_json() {
+        let mut headers = HeaderMap::new();
+        headers.append("set-cookie", HeaderValue::from_static("a=1"));
+        headers.append("set-cookie", HeaderValue::from_static("b=2"));
+        headers.append("content-type", HeaderValue::from_static("application/json"));
+        let response =
+            HttpResponse::new(String::from("HTTP/1.1"), 201, headers, String::from(r#"{"id":1}"#));
+
+        let exchange = Exchange {
+            method: "POST",
+            url: &Url::parse("https://example.com/users").unwrap(),
+            headers: &HeaderMap::new(),
+            body: Some("name=fire"),
+            response: &response,
+            started: Utc::now(),
+            duration: Duration::from_millis(12),
+        };
+
+        let output = super::json(&exchange, None, Some("application/json"), &[String::from("dev")]);
+        assert_eq!(json!("name=fire"), output["request"]["body"]);
+        assert_eq!(json!({ "id": 1 }), output["response"]["body"]);
+        assert_eq!(json!(["a=1", "b=2"]), output["response"]["headers"]["set-cookie"]);
+        assert_eq!(json!(201), output["response"]["status"]);
+        assert_eq!(json!(["dev"]), output["environments"]);
+    }
+}
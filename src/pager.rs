@@ -0,0 +1,73 @@
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+
+/// When output is shown in a pager
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Paging {
+    /// Only when stdout is a terminal and the output is taller than the terminal
+    Auto,
+    /// Whenever stdout is a terminal
+    Always,
+    Never,
+}
+
+/// Show content in a pager, returning false if there is no pager or if the pager could not be
+/// started, in which case the content should be printed as usual instead. The configured command,
+/// such as from `$FIRE_PAGER`, takes precedence over `$PAGER`.
+pub fn page(content: &[u8], configured: Option<String>) -> bool {
+    let command: Vec<String> = match command(configured, std::env::var("PAGER").ok()) {
+        Some(command) => command,
+        None => return false,
+    };
+
+    let (program, args) = command.split_first().unwrap();
+    let mut pager = Command::new(program);
+    pager.args(args).stdin(Stdio::piped());
+
+    // Make sure colors are shown when `less` is used without `-R`, such as with `PAGER=less`
+    if std::env::var_os("LESS").is_none() {
+        pager.env("LESS", "R");
+    }
+
+    let mut child: Child = match pager.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            log::warn!("Unable to start pager '{program}': {e}");
+            return false;
+        }
+    };
+
+    // Writing fails if the pager is closed before all content is read, which is fine
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(content);
+    }
+
+    if let Err(e) = child.wait() {
+        log::warn!("Pager '{program}' failed: {e}");
+    }
+
+    true
+}
+
+/// Command for the pager, where the configured command takes precedence over `$PAGER`, and
+/// `less -R` is used if neither is set. Setting either of them to an empty value disables paging.
+fn command(configured: Option<String>, pager: Option<String>) -> Option<Vec<String>> {
+    let command: String = configured.or(pager).unwrap_or_else(|| String::from("less -R"));
+    shell_words::split(&command).ok().filter(|words| !words.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::command;
+
+    #[test]
+    fn test_pager_command() {
+        let words = |words: &[&str]| Some(words.iter().map(|w| w.to_string()).collect::<Vec<_>>());
+        let some = |value: &str| Some(String::from(value));
+
+        assert_eq!(words(&["less", "-R"]), command(None, None));
+        assert_eq!(words(&["more"]), command(None, some("more")));
+        assert_eq!(words(&["bat", "--plain"]), command(some("bat --plain"), some("more")));
+        assert_eq!(None, command(some(""), some("more")));
+    }
+}
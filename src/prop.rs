@@ -12,6 +12,8 @@ pub struct Property {
 pub enum Source {
     EnvVar,
     File(usize),
+    /// Declared in the request file itself
+    Declaration,
     Arg,
 }
 
@@ -26,10 +28,13 @@ impl Ord for Source {
         match (self, other) {
             (Source::EnvVar, Source::EnvVar) => Ordering::Equal,
             (Source::Arg, Source::Arg) => Ordering::Equal,
+            (Source::Declaration, Source::Declaration) => Ordering::Equal,
             (Source::File(d0), Source::File(d1)) => d1.cmp(d0),
             (Source::File(_), Source::EnvVar) => Ordering::Less,
             (Source::EnvVar, _) => Ordering::Greater,
-            (Source::File(_), Source::Arg) => Ordering::Greater,
+            (Source::File(_), _) => Ordering::Greater,
+            (Source::Declaration, Source::Arg) => Ordering::Greater,
+            (Source::Declaration, _) => Ordering::Less,
             (Source::Arg, _) => Ordering::Less,
         }
     }
@@ -72,6 +77,8 @@ pub fn from_file(path: &Path) -> Result<Vec<Property>, ParsePropertyError> {
     content
         .lines()
         .filter(|line| !line.trim().is_empty())
+        // Lines starting with `#` are comments
+        .filter(|line| !line.trim_start().starts_with('#'))
         .map(Property::from_str)
         .map(|prop| prop.map(|p| p.with_source(source)))
         .collect()
@@ -83,6 +90,7 @@ fn source(path: &Path) -> Source {
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub enum ParsePropertyError {
     Entry(String),
     Key(String),
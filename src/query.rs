@@ -0,0 +1,194 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::error::FireError;
+
+/// A query for a value in a JSON document, using a subset of the syntax of jq and JSONPath, like
+/// `.data.items[0].id`, `$.data.items[*].id` or `.["key with spaces"]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    /// Index of an element in an array, where a negative index counts from the end
+    Index(i64),
+    /// Every element in an array, or every value in an object
+    All,
+}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Segment::Key(key) if key.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+                write!(f, ".{key}")
+            }
+            Segment::Key(key) => write!(f, "[{key:?}]"),
+            Segment::Index(index) => write!(f, "[{index}]"),
+            Segment::All => write!(f, "[*]"),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let invalid = |msg: &str| format!("Invalid query '{input}': {msg}");
+        let mut rest: &str = input.trim().strip_prefix('$').unwrap_or(input.trim());
+        let mut segments: Vec<Segment> = Vec::new();
+
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('[') {
+                let end: usize = after.find(']').ok_or_else(|| invalid("missing ]"))?;
+                let inner: &str = after[..end].trim();
+                let segment: Segment = match inner {
+                    "" | "*" => Segment::All,
+                    quoted
+                        if quoted.len() >= 2
+                            && (quoted.starts_with('"') || quoted.starts_with('\'')) =>
+                    {
+                        Segment::Key(quoted[1..quoted.len() - 1].to_string())
+                    }
+                    index => Segment::Index(index.parse().map_err(|_| invalid("invalid index"))?),
+                };
+                segments.push(segment);
+                rest = &after[end + 1..];
+            } else if let Some(after) = rest.strip_prefix('.') {
+                let end: usize = after.find(['.', '[']).unwrap_or(after.len());
+                match &after[..end] {
+                    "" => {}
+                    "*" => segments.push(Segment::All),
+                    key => segments.push(Segment::Key(key.to_string())),
+                }
+                rest = &after[end..];
+            } else {
+                return Err(invalid("expected . or ["));
+            }
+        }
+
+        Ok(Query { segments })
+    }
+}
+
+impl Query {
+    /// Apply the query to a JSON document. A query where any part of the path is missing results
+    /// in an error which says where the path ended.
+    pub fn apply(&self, value: &Value) -> Result<Value, String> {
+        apply(&self.segments, value, String::new())
+    }
+}
+
+/// Filter a JSON body with a query, returning the result as JSON
+pub fn filter(query: &Query, body: &str) -> Result<String, FireError> {
+    let json: Value = serde_json::from_str(body)
+        .map_err(|_| FireError::Query(String::from("Body of response is not JSON")))?;
+    let result: Value = query.apply(&json).map_err(FireError::Query)?;
+    Ok(result.to_string())
+}
+
+fn apply(segments: &[Segment], value: &Value, path: String) -> Result<Value, String> {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => return Ok(value.clone()),
+    };
+
+    let here: String = format!("{path}{segment}");
+    let display = |path: &str| match path {
+        "" => String::from("."),
+        path => path.to_string(),
+    };
+
+    match (segment, value) {
+        (Segment::Key(key), Value::Object(map)) => match map.get(key) {
+            Some(value) => apply(rest, value, here),
+            None => Err(format!("No key '{key}' at {}", display(&path))),
+        },
+        (Segment::Index(index), Value::Array(items)) => {
+            let position: Option<usize> = match *index {
+                i if i < 0 => items.len().checked_sub(i.unsigned_abs() as usize),
+                i => Some(i as usize),
+            };
+            match position.and_then(|i| items.get(i)) {
+                Some(value) => apply(rest, value, here),
+                None => Err(format!(
+                    "No element {index} at {}, which has {} elements",
+                    display(&path),
+                    items.len()
+                )),
+            }
+        }
+        (Segment::All, Value::Array(items)) => items
+            .iter()
+            .enumerate()
+            .map(|(i, value)| apply(rest, value, format!("{path}[{i}]")))
+            .collect::<Result<Vec<Value>, String>>()
+            .map(Value::Array),
+        (Segment::All, Value::Object(map)) => map
+            .iter()
+            .map(|(key, value)| apply(rest, value, format!("{path}{}", Segment::Key(key.clone()))))
+            .collect::<Result<Vec<Value>, String>>()
+            .map(Value::Array),
+        (_, value) => {
+            Err(format!("Cannot get {segment} from {} at {}", kind(value), display(&path)))
+        }
+    }
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use serde_json::json;
+
+    use super::Query;
+
+    #[test]
+    fn test_query_json() {
+        let doc = json!({
+            "data": {
+                "items": [{ "id": 1, "tags": ["a"] }, { "id": 2, "tags": [] }],
+                "total count": 2
+            }
+        });
+
+        let query = |q: &str| Query::from_str(q).unwrap().apply(&doc);
+
+        assert_eq!(Ok(json!(1)), query(".data.items[0].id"));
+        assert_eq!(Ok(json!(2)), query("$.data.items[-1].id"));
+        assert_eq!(Ok(json!([1, 2])), query(".data.items[*].id"));
+        assert_eq!(Ok(json!([1, 2])), query(".data.items[].id"));
+        assert_eq!(Ok(json!(2)), query(r#".data["total count"]"#));
+        assert_eq!(Ok(doc.clone()), query("."));
+
+        assert_eq!(
+            Err(String::from("No element 5 at .data.items, which has 2 elements")),
+            query(".data.items[5]")
+        );
+        assert_eq!(
+            Err(String::from("No key 'name' at .data.items[1]")),
+            query(".data.items[1].name")
+        );
+        assert_eq!(
+            Err(String::from("Cannot get .id from a number at .data.items[0].id")),
+            query(".data.items[0].id.id")
+        );
+        assert!(Query::from_str(".data[").is_err());
+        assert!(Query::from_str("data").is_err());
+    }
+}
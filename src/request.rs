@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use http::HeaderMap;
+use httpx::grpc::GrpcRequest;
+use httpx::{HttpRequest, HttpResponse};
+use url::Url;
+
+use crate::defaults::Defaults;
+use crate::error::FireError;
+use crate::http_file;
+
+const JSON: &str = "application/json";
+
+/// A rendered request from a request file, which is either a plain HTTP request or a gRPC call
+#[derive(Debug, Clone)]
+pub enum Request {
+    Http(HttpRequest),
+    Grpc(GrpcRequest),
+}
+
+impl Request {
+    /// Parse the (already rendered) content of the request file at `path`. A request file is a
+    /// gRPC request rather than a HTTP request if it has a `grpc` section. Requests from `.http`
+    /// files are always HTTP requests. Defaults are only applied to HTTP requests.
+    pub fn parse(content: &str, path: &Path, defaults: &Defaults) -> Result<Request, FireError> {
+        if http_file::is_http_file(path) {
+            let mut request: HttpRequest = http_file::request(content)?;
+            defaults.apply(&mut request)?;
+            request.set_default_headers().unwrap();
+            return Ok(Request::Http(request));
+        }
+
+        let yaml: serde_yaml::Value = serde_yaml::from_str(content)
+            .map_err(|e| FireError::Other(format!("Invalid request file: {e}")))?;
+
+        if yaml.get("grpc").is_some() {
+            let mut request: GrpcRequest = GrpcRequest::from_str(content)
+                .map_err(|e| FireError::Other(format!("Invalid gRPC request: {e}")))?;
+            if let Some(dir) = path.parent() {
+                request.resolve_paths(dir);
+            }
+            Ok(Request::Grpc(request))
+        } else {
+            let mut request: HttpRequest = HttpRequest::from_str(content)
+                .map_err(|e| FireError::Other(format!("Invalid request: {e}")))?;
+            add_query(&mut request, &query(&yaml)?)?;
+            defaults.apply(&mut request)?;
+
+            // Add default header, if missing
+            request.set_default_headers().unwrap();
+            Ok(Request::Http(request))
+        }
+    }
+
+    pub fn method(&self) -> String {
+        match self {
+            Request::Http(request) => request.method().to_string(),
+            Request::Grpc(_) => String::from("POST"),
+        }
+    }
+
+    pub fn url(&self) -> Url {
+        match self {
+            Request::Http(request) => request.url().unwrap(),
+            Request::Grpc(request) => request.url().unwrap().join(&request.path()).unwrap(),
+        }
+    }
+
+    /// Title of the request, as it is printed before the headers and body of the request
+    pub fn title(&self) -> String {
+        match self {
+            Request::Http(request) => match request.unix_socket() {
+                Some(socket) => format!("{} {} ({})", self.method(), self.url(), socket.display()),
+                None => format!("{} {}", self.method(), self.url()),
+            },
+            Request::Grpc(_) => format!("gRPC {}", self.url()),
+        }
+    }
+
+    pub fn headers(&self) -> HeaderMap {
+        match self {
+            Request::Http(request) => request.headers(),
+            Request::Grpc(request) => request.headers(),
+        }
+    }
+
+    /// Body that is sent with the request, where the message of a gRPC call is given as JSON
+    pub fn body(&self) -> Result<Option<String>, FireError> {
+        match self {
+            Request::Http(request) => Ok(request.sent_body().map(String::from)),
+            Request::Grpc(request) => Ok(Some(request.message()?.to_string())),
+        }
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        match self {
+            Request::Http(request) => request.header("content-type"),
+            Request::Grpc(_) => Some(JSON),
+        }
+    }
+
+    /// Content type of the body of the response. The reply to a gRPC call is always decoded to
+    /// JSON, regardless of the content type of the response.
+    pub fn response_content_type<'a>(&self, response: &'a HttpResponse) -> Option<&'a str> {
+        match self {
+            Request::Http(_) => response.header("content-type"),
+            Request::Grpc(_) => Some(JSON),
+        }
+    }
+
+    /// Execute the request, optionally measuring the time spent in each phase of the request.
+    /// Timings are not available for gRPC calls.
+    pub fn call(&self, timeout: Duration, timings: bool) -> Result<HttpResponse, FireError> {
+        match self {
+            Request::Http(request) if timings => {
+                Ok(httpx::request::call_with_timings(request.clone(), timeout)?)
+            }
+            Request::Http(request) => Ok(httpx::request::call(request.clone(), timeout)?),
+            Request::Grpc(request) => Ok(httpx::grpc::call(request.clone(), timeout)?),
+        }
+    }
+}
+
+/// Query parameters from the `query` section of a request file, if any
+fn query(yaml: &serde_yaml::Value) -> Result<BTreeMap<String, String>, FireError> {
+    let query: &serde_yaml::Mapping = match yaml.get("query") {
+        Some(serde_yaml::Value::Mapping(query)) => query,
+        Some(_) => {
+            return Err(FireError::Other(String::from("Invalid request: query must be a map")))
+        }
+        None => return Ok(BTreeMap::new()),
+    };
+
+    query
+        .iter()
+        .map(|(key, value)| {
+            let scalar = |value: &serde_yaml::Value| match value {
+                serde_yaml::Value::String(value) => Some(value.clone()),
+                serde_yaml::Value::Number(value) => Some(value.to_string()),
+                serde_yaml::Value::Bool(value) => Some(value.to_string()),
+                _ => None,
+            };
+            match (scalar(key), scalar(value)) {
+                (Some(key), Some(value)) => Ok((key, value)),
+                _ => Err(FireError::Other(format!(
+                    "Invalid request: invalid query parameter {key:?}"
+                ))),
+            }
+        })
+        .collect()
+}
+
+/// Add query parameters to the URL of a request, unless the URL already has a parameter with the
+/// same name
+pub fn add_query(
+    request: &mut HttpRequest,
+    query: &BTreeMap<String, String>,
+) -> Result<(), FireError> {
+    if query.is_empty() {
+        return Ok(());
+    }
+
+    let present: HashSet<String> = match request.url() {
+        Ok(url) => url.query_pairs().map(|(key, _)| key.into_owned()).collect(),
+        Err(e) => return Err(FireError::Other(format!("Invalid URL in request: {e}"))),
+    };
+    let query: String = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(query.iter().filter(|(key, _)| !present.contains(*key)))
+        .finish();
+    if !query.is_empty() {
+        let url: &str = request.raw_url();
+        let separator: char = if url.contains('?') { '&' } else { '?' };
+        let url: String = format!("{url}{separator}{query}");
+        request.set_url(url);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,292 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use httpx::HttpResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use termcolor::{Color, StandardStream};
+
+use crate::error::FireError;
+use crate::io::{write_color, writeln};
+
+/// Headers that are always part of a snapshot
+const DEFAULT_HEADERS: [&str; 1] = ["content-type"];
+const IGNORED: &str = "<ignored>";
+const WILDCARD: &str = "*";
+
+/// A normalized response, as stored in a snapshot file next to the request file. Ignore paths are
+/// stored in the snapshot too, so they only have to be given once, when the snapshot is created.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    status: u16,
+    headers: Map<String, Value>,
+    body: Value,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ignore: Vec<String>,
+}
+
+/// A difference between a snapshot and a response, where the path is the location in the
+/// snapshot, such as `body.users.0.name`
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Added(String, Value),
+    Removed(String, Value),
+    Changed(String, Value, Value),
+}
+
+/// Options for how a response is compared to its snapshot
+pub struct Options<'a> {
+    pub headers: &'a [String],
+    pub ignore: &'a [String],
+    pub update: bool,
+}
+
+impl Snapshot {
+    fn new(response: &HttpResponse, headers: &BTreeSet<String>, ignore: Vec<String>) -> Snapshot {
+        let headers: Map<String, Value> = headers
+            .iter()
+            .filter_map(|key| Some((key.clone(), Value::from(response.header(key)?))))
+            .collect();
+
+        // JSON bodies are compared structurally, any other body is compared as text
+        let body: Value = serde_json::from_str(response.body())
+            .unwrap_or_else(|_| Value::String(response.body().to_string()));
+
+        let mut snapshot = Snapshot {
+            status: response.status(),
+            headers,
+            body,
+            ignore,
+        };
+        snapshot.apply_ignore();
+        snapshot
+    }
+
+    fn apply_ignore(&mut self) {
+        let mut content: Value = self.content();
+        for path in &self.ignore {
+            let path: String = path.replace('[', ".").replace(']', "");
+            let segments: Vec<&str> = path.split('.').filter(|s| !s.is_empty()).collect();
+            ignore(&mut content, &segments);
+        }
+        self.headers = content["headers"].as_object().cloned().unwrap_or_default();
+        self.body = content["body"].take();
+    }
+
+    /// The parts of the snapshot that are compared with a response
+    fn content(&self) -> Value {
+        serde_json::json!({
+            "status": self.status,
+            "headers": self.headers,
+            "body": self.body,
+        })
+    }
+
+    fn read(path: &Path) -> Result<Snapshot, FireError> {
+        let content: String =
+            std::fs::read_to_string(path).map_err(|e| FireError::GenericIO(e.to_string()))?;
+        serde_json::from_str(&content)
+            .map_err(|e| FireError::Other(format!("Invalid snapshot {:?}: {e}", path)))
+    }
+
+    fn write(&self, path: &Path) -> Result<(), FireError> {
+        let content: String = serde_json::to_string_pretty(self).unwrap() + "\n";
+        std::fs::write(path, content).map_err(|e| FireError::GenericIO(e.to_string()))
+    }
+}
+
+/// Path of the snapshot for a request file, like `get_user.snapshot.json` for `get_user.yml`.
+/// Requests in `.http` files that are selected by name have one snapshot per request.
+pub fn path(request_file: &Path, name: Option<&str>) -> PathBuf {
+    let stem: String = request_file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let file_name: String = match name {
+        Some(name) => format!("{stem}.{name}.snapshot.json"),
+        None => format!("{stem}.snapshot.json"),
+    };
+
+    request_file.with_file_name(file_name)
+}
+
+/// Compare a response with the snapshot at `path`. The snapshot is created if it does not exist,
+/// or if it should be updated. Any differences are printed, and result in an error.
+pub fn verify(
+    stdout: &mut StandardStream,
+    path: &Path,
+    response: &HttpResponse,
+    options: &Options,
+) -> Result<(), FireError> {
+    let existing: Option<Snapshot> = match path.exists() {
+        true => Some(Snapshot::read(path)?),
+        false => None,
+    };
+
+    let mut headers: BTreeSet<String> = DEFAULT_HEADERS.iter().map(|h| h.to_string()).collect();
+    headers.extend(options.headers.iter().map(|h| h.to_lowercase()));
+
+    let mut ignore: Vec<String> = options.ignore.to_vec();
+    if let Some(snapshot) = &existing {
+        headers.extend(snapshot.headers.keys().cloned());
+        ignore.extend(snapshot.ignore.iter().cloned());
+    }
+    ignore.sort();
+    ignore.dedup();
+
+    let actual = Snapshot::new(response, &headers, ignore);
+
+    let expected: Snapshot = match existing {
+        Some(snapshot) if !options.update => snapshot,
+        _ => {
+            actual.write(path)?;
+            writeln(stdout, &format!("Saved snapshot to {}", path.display()));
+            return Ok(());
+        }
+    };
+
+    // Paths that are ignored now may not have been ignored when the snapshot was saved
+    let mut expected = Snapshot {
+        ignore: actual.ignore.clone(),
+        ..expected
+    };
+    expected.apply_ignore();
+
+    let changes: Vec<Change> = diff(&expected.content(), &actual.content());
+    if changes.is_empty() {
+        write_color(stdout, "Response matches snapshot", Some(Color::Green));
+        writeln(stdout, &format!(" {}", path.display()));
+        return Ok(());
+    }
+
+    writeln(stdout, "");
+    for change in &changes {
+        match change {
+            Change::Added(path, value) => {
+                write_color(stdout, &format!("+ {path}: {value}\n"), Some(Color::Green))
+            }
+            Change::Removed(path, value) => {
+                write_color(stdout, &format!("- {path}: {value}\n"), Some(Color::Red))
+            }
+            Change::Changed(path, old, new) => {
+                write_color(stdout, &format!("- {path}: {old}\n"), Some(Color::Red));
+                write_color(stdout, &format!("+ {path}: {new}\n"), Some(Color::Green));
+            }
+        }
+    }
+    writeln(stdout, "");
+
+    Err(FireError::Snapshot(path.to_path_buf()))
+}
+
+/// Replace the value at every location matching `path` with a placeholder, where `*` matches any
+/// key in an object or any index in an array
+fn ignore(value: &mut Value, path: &[&str]) {
+    let (segment, rest) = match path.split_first() {
+        Some(split) => split,
+        None => {
+            *value = Value::String(String::from(IGNORED));
+            return;
+        }
+    };
+
+    match value {
+        Value::Object(map) if *segment == WILDCARD => {
+            map.values_mut().for_each(|value| ignore(value, rest))
+        }
+        Value::Object(map) => {
+            if let Some(value) = map.get_mut(*segment) {
+                ignore(value, rest)
+            }
+        }
+        Value::Array(items) if *segment == WILDCARD => {
+            items.iter_mut().for_each(|value| ignore(value, rest))
+        }
+        Value::Array(items) => {
+            if let Some(value) = segment.parse().ok().and_then(|i: usize| items.get_mut(i)) {
+                ignore(value, rest)
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Structural difference between two JSON values
+pub fn diff(expected: &Value, actual: &Value) -> Vec<Change> {
+    let mut changes: Vec<Change> = Vec::new();
+    diff_at("", expected, actual, &mut changes);
+    changes
+}
+
+fn diff_at(path: &str, expected: &Value, actual: &Value, changes: &mut Vec<Change>) {
+    let child = |key: &str| match path {
+        "" => key.to_string(),
+        _ => format!("{path}.{key}"),
+    };
+
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, value) in expected {
+                match actual.get(key) {
+                    Some(other) => diff_at(&child(key), value, other, changes),
+                    None => changes.push(Change::Removed(child(key), value.clone())),
+                }
+            }
+            for (key, value) in actual {
+                if !expected.contains_key(key) {
+                    changes.push(Change::Added(child(key), value.clone()));
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            for (i, value) in expected.iter().enumerate() {
+                match actual.get(i) {
+                    Some(other) => diff_at(&child(&i.to_string()), value, other, changes),
+                    None => changes.push(Change::Removed(child(&i.to_string()), value.clone())),
+                }
+            }
+            for (i, value) in actual.iter().enumerate().skip(expected.len()) {
+                changes.push(Change::Added(child(&i.to_string()), value.clone()));
+            }
+        }
+        (expected, actual) if expected != actual => {
+            changes.push(Change::Changed(path.to_string(), expected.clone(), actual.clone()))
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{diff, ignore, Change};
+
+    #[test]
+    fn test_diff_with_ignored_paths() {
+        let mut expected: Value = json!({
+            "status": 200,
+            "body": { "id": 1, "name": "fire", "tags": ["a", "b"], "created": "2024-01-01" }
+        });
+        let mut actual: Value = json!({
+            "status": 200,
+            "body": { "id": 2, "name": "flame", "tags": ["a"], "created": "2024-05-01", "new": true }
+        });
+
+        for value in [&mut expected, &mut actual] {
+            ignore(value, &["body", "id"]);
+            ignore(value, &["*", "created"]);
+        }
+
+        let changes: Vec<Change> = diff(&expected, &actual);
+        assert_eq!(
+            vec![
+                Change::Changed(String::from("body.name"), json!("fire"), json!("flame")),
+                Change::Removed(String::from("body.tags.1"), json!("b")),
+                Change::Added(String::from("body.new"), json!(true)),
+            ],
+            changes
+        );
+    }
+}
@@ -1,35 +1,127 @@
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-lazy_static! {
-    static ref VAR_REGEX: Regex = Regex::new(r"\{\{[A-Za-z0-9_-]{1,32}\}\}").unwrap();
+use handlebars::template::{HelperTemplate, Parameter, Template, TemplateElement};
+use handlebars::Path;
+
+use crate::helpers;
+
+/// Helpers that are built into Handlebars
+const BUILT_IN: [&str; 17] = [
+    "if", "unless", "each", "with", "lookup", "raw", "log", "eq", "ne", "gt", "gte", "lt", "lte",
+    "and", "or", "not", "len",
+];
+
+/// Find the keys that must have a value for a template to be rendered, given the values in
+/// `vars`. Conditions of `{{#if}}` and `{{#unless}}` and the first argument of `default` are
+/// optional, and keys in a section that is not rendered are not required.
+pub fn find_keys(template: &Template, vars: &HashMap<String, String>) -> HashSet<String> {
+    let mut keys: HashSet<String> = HashSet::new();
+    visit(template, vars, &mut keys);
+    keys
+}
+
+fn visit(template: &Template, vars: &HashMap<String, String>, keys: &mut HashSet<String>) {
+    for element in &template.elements {
+        match element {
+            TemplateElement::Expression(expr) | TemplateElement::HtmlExpression(expr) => {
+                expression(expr, vars, keys)
+            }
+            TemplateElement::HelperBlock(block) => self::block(block, vars, keys),
+            _ => {}
+        }
+    }
+}
+
+fn expression(expr: &HelperTemplate, vars: &HashMap<String, String>, keys: &mut HashSet<String>) {
+    let helper: Option<&str> = expr.name.as_name().filter(|name| is_helper(name));
+    let params: &[Parameter] = match helper {
+        Some("default") => expr.params.get(1..).unwrap_or_default(),
+        Some(_) => &expr.params,
+        None => std::slice::from_ref(&expr.name),
+    };
+
+    for param in params.iter().chain(expr.hash.values()) {
+        parameter(param, vars, keys);
+    }
+}
+
+fn block(block: &HelperTemplate, vars: &HashMap<String, String>, keys: &mut HashSet<String>) {
+    let condition: Option<bool> = match block.params.first() {
+        Some(Parameter::Path(path)) => key(path).map(|key| truthy(vars.get(key))),
+        _ => None,
+    };
+
+    let sections: Vec<&Option<Template>> = match (block.name.as_name(), condition) {
+        (Some("if"), Some(true)) | (Some("unless"), Some(false)) => vec![&block.template],
+        (Some("if"), Some(false)) | (Some("unless"), Some(true)) => vec![&block.inverse],
+        _ => {
+            for param in block.params.iter().chain(block.hash.values()) {
+                parameter(param, vars, keys);
+            }
+            vec![&block.template, &block.inverse]
+        }
+    };
+
+    for template in sections.into_iter().flatten() {
+        visit(template, vars, keys);
+    }
+}
+
+fn parameter(param: &Parameter, vars: &HashMap<String, String>, keys: &mut HashSet<String>) {
+    match param {
+        Parameter::Path(path) => keys.extend(key(path).map(String::from)),
+        Parameter::Subexpression(expr) => {
+            if let TemplateElement::Expression(expr) = expr.as_element() {
+                expression(expr, vars, keys)
+            }
+        }
+        Parameter::Name(_) | Parameter::Literal(_) => {}
+    }
 }
 
-pub fn find_keys(template: &str) -> HashSet<String> {
-    VAR_REGEX
-        .find_iter(template)
-        .map(|m: regex::Match| trim_braces(m.as_str()).to_string())
-        .collect()
+/// Key for a path that refers to a variable, such as `{{TOKEN}}`, but not for paths such as
+/// `{{this}}`, `{{@index}}` or `{{../name}}`
+fn key(path: &Path) -> Option<&str> {
+    match path {
+        Path::Relative((_, raw)) if raw != "this" && !raw.contains(['.', '/']) => Some(raw),
+        _ => None,
+    }
+}
+
+fn truthy(value: Option<&String>) -> bool {
+    value.is_some_and(|value| !value.is_empty())
 }
 
-fn trim_braces(input: &str) -> &str {
-    let end: usize = input.len() - 2;
-    input.get(2..end).unwrap()
+fn is_helper(name: &str) -> bool {
+    helpers::NAMES.contains(&name) || BUILT_IN.contains(&name)
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
+    use std::collections::{HashMap, HashSet};
+
+    use handlebars::template::Template;
 
     use crate::templ;
 
     #[test]
     fn find_template_keys() {
-        let template = "{{FOO}} {{}}- {{{}}} {{  }} {{BAR}}";
-        let keys: HashSet<String> = templ::find_keys(&template);
-        let expected: HashSet<String> =
-            [String::from("FOO"), String::from("BAR")].into_iter().collect();
-        assert_eq!(expected, keys);
+        let keys = |template: &str, vars: &[&str]| -> HashSet<String> {
+            let template: Template = Template::compile(template).unwrap();
+            let vars: HashMap<String, String> =
+                vars.iter().map(|key| (key.to_string(), String::from("value"))).collect();
+            templ::find_keys(&template, &vars)
+        };
+        let set =
+            |keys: &[&str]| -> HashSet<String> { keys.iter().map(|k| k.to_string()).collect() };
+
+        assert_eq!(set(&["FOO", "BAR"]), keys("{{FOO}} {{BAR}} {{uuid}}", &[]));
+        assert_eq!(set(&["SECRET", "BODY"]), keys("{{hmac SECRET (upper BODY)}}", &[]));
+        assert_eq!(set(&["SIZE"]), keys(r#"{{default PAGE_SIZE SIZE}} {{default PAGE "1"}}"#, &[]));
+
+        let template = "{{#if DEBUG}}{{TRACE_ID}}{{else}}{{USER}}{{/if}}";
+        assert_eq!(set(&["USER"]), keys(template, &[]));
+        assert_eq!(set(&["TRACE_ID"]), keys(template, &["DEBUG"]));
+        assert_eq!(set(&["USER"]), keys("{{#unless DEBUG}}{{USER}}{{/unless}}", &[]));
     }
 }
@@ -1,9 +1,32 @@
-use handlebars::{no_escape, Handlebars};
+use handlebars::template::Template;
+use handlebars::Handlebars;
+use serde_yaml::{Mapping, Value};
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::path::PathBuf;
 
-use crate::{prop::Property, templ};
+use crate::{helpers, prop::Property, templ};
 
+/// Characters around each substituted value in rendered output, so that the value can be escaped
+/// according to where it ended up. They are from the private use area of Unicode, and are removed
+/// before the output is used.
+const VALUE_START: char = '\u{E000}';
+const VALUE_END: char = '\u{E001}';
+
+/// Where a template is rendered, which decides how substituted values are escaped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    /// Values are inserted as they are
+    Text,
+    /// A URL, where values in the path, query and fragment are percent-encoded
+    Url,
+    /// A JSON body, where values inside JSON strings are escaped
+    Json,
+    /// Values are left marked, to be escaped once their context is known
+    Marked,
+}
+
+/// Render a template as text, where substituted values are inserted as they are
 pub fn substitution(
     input: String,
     vars: Vec<Property>,
@@ -11,14 +34,232 @@ pub fn substitution(
     use_colors: bool,
     trim: bool,
 ) -> Result<String, SubstitutionError> {
-    let keys: HashSet<String> = templ::find_keys(&input);
-    let vars: HashMap<String, String> =
-        resolve_values(interactive, use_colors, trim, keys, merge(vars))?;
+    let inputs: Vec<(String, Context)> = vec![(input, Context::Text)];
+    let mut rendered: Vec<String> = render(inputs, vars, interactive, use_colors, trim)?;
+    Ok(rendered.pop().unwrap())
+}
+
+/// Render a YAML document where templates are only found in string scalars, such as a request
+/// file, by rendering each string scalar on its own. Substituted values can then contain any
+/// character, such as `"`, `:` or a newline, without changing the structure of the document.
+/// Values are also escaped where they end up in the `url` and in a JSON `body`, see [escape].
+///
+/// Any other content, such as a document with a `{{#if}}` section around several lines, is
+/// rendered as text, see [text_substitution].
+pub fn yaml_substitution(
+    input: String,
+    vars: Vec<Property>,
+    interactive: bool,
+    use_colors: bool,
+    trim: bool,
+) -> Result<String, SubstitutionError> {
+    let templates: usize = input.matches("{{").count();
+    let yaml: Mapping = match serde_yaml::from_str(&input) {
+        _ if templates == 0 => return Ok(input),
+        Ok(Value::Mapping(yaml)) => yaml,
+        _ => return text_substitution(input, vars, interactive, use_colors, trim),
+    };
+
+    let json: bool = has_json_body(&yaml);
+    let mut scalars: Vec<(String, Context)> = Vec::new();
+    let yaml: Mapping = map_document(yaml, json, &mut |scalar: String, context: Context| {
+        if scalar.contains("{{") {
+            scalars.push((scalar.clone(), context));
+        }
+        scalar
+    });
+
+    // Some template is outside of a string scalar, such as in a comment or around several lines
+    if scalars.iter().map(|(scalar, _)| scalar.matches("{{").count()).sum::<usize>() != templates {
+        return text_substitution(input, vars, interactive, use_colors, trim);
+    }
+
+    let mut rendered = render(scalars, vars, interactive, use_colors, trim)?.into_iter();
+    let yaml: Mapping =
+        map_document(yaml, json, &mut |scalar: String, _| match scalar.contains("{{") {
+            true => rendered.next().unwrap(),
+            false => scalar,
+        });
+
+    Ok(serde_yaml::to_string(&yaml).unwrap())
+}
+
+/// Render a request file as text, for when some template is outside of a string scalar. If the
+/// rendered file is a YAML mapping, the `url` and a JSON `body` are escaped as in
+/// [yaml_substitution], while values anywhere else are inserted as they are.
+fn text_substitution(
+    input: String,
+    vars: Vec<Property>,
+    interactive: bool,
+    use_colors: bool,
+    trim: bool,
+) -> Result<String, SubstitutionError> {
+    // The file is rendered with the same values both as text, and with values marked so that
+    // they can be escaped once it is known where they are
+    let inputs: Vec<(String, Context)> =
+        vec![(input.clone(), Context::Text), (input, Context::Marked)];
+    let mut rendered = render(inputs, vars, interactive, use_colors, trim)?.into_iter();
+    let (text, marked): (String, String) = (rendered.next().unwrap(), rendered.next().unwrap());
+
+    let (mut yaml, marked): (Mapping, Mapping) =
+        match (serde_yaml::from_str(&text), serde_yaml::from_str(&marked)) {
+            (Ok(yaml), Ok(marked)) => (yaml, marked),
+            _ => return Ok(text),
+        };
+
+    let body: Context = match has_json_body(&yaml) {
+        true => Context::Json,
+        false => Context::Text,
+    };
+    for (key, context) in [("url", Context::Url), ("body", body)] {
+        if let Some(Value::String(value)) = marked.get(key) {
+            yaml.insert(Value::from(key), Value::String(escape(value, context)));
+        }
+    }
+
+    Ok(serde_yaml::to_string(&yaml).unwrap())
+}
+
+/// Render templates with the same values, where any missing values are resolved once for all
+/// templates
+fn render(
+    inputs: Vec<(String, Context)>,
+    vars: Vec<Property>,
+    interactive: bool,
+    use_colors: bool,
+    trim: bool,
+) -> Result<Vec<String>, SubstitutionError> {
+    let templates: Vec<(Template, Context)> = inputs
+        .iter()
+        .map(|(input, context)| match Template::compile(input) {
+            Ok(template) => Ok((template, *context)),
+            Err(e) => Err(SubstitutionError::Rendering(e.to_string())),
+        })
+        .collect::<Result<_, _>>()?;
+
+    let vars: HashMap<String, String> = merge(vars);
+    let keys: HashSet<String> = templates
+        .iter()
+        .flat_map(|(template, _)| templ::find_keys(template, &vars))
+        .collect();
+    let vars: HashMap<String, String> = resolve_values(interactive, use_colors, trim, keys, vars)?;
+
     let mut reg = Handlebars::new();
-    reg.register_escape_fn(no_escape);
+    reg.register_escape_fn(|value: &str| format!("{VALUE_START}{value}{VALUE_END}"));
     reg.set_strict_mode(true);
-    reg.register_template_string("template", input).unwrap();
-    reg.render("template", &vars).map_err(|_| SubstitutionError::Rendering)
+    helpers::register(&mut reg);
+
+    templates
+        .into_iter()
+        .map(|(template, context)| {
+            reg.register_template("template", template);
+            match reg.render("template", &vars) {
+                Ok(rendered) if context == Context::Marked => Ok(rendered),
+                Ok(rendered) => Ok(escape(&rendered, context)),
+                Err(e) => Err(SubstitutionError::Rendering(e.desc)),
+            }
+        })
+        .collect()
+}
+
+/// Escape each substituted value in rendered output according to where it is. In a URL, values
+/// in the path, query and fragment are percent-encoded, while values in the scheme and host, such
+/// as a base URL, are not. In a JSON body, values inside a string are escaped as JSON, while values
+/// elsewhere, such as a number or an array, are inserted as they are. Values from the `json` and
+/// `urlencode` helpers, and from `{{{triple-stash}}}`, are never escaped.
+fn escape(rendered: &str, context: Context) -> String {
+    let mut out = String::with_capacity(rendered.len());
+    let mut value = String::new();
+    // Values from helpers in subexpressions can be nested within other values
+    let mut depth: usize = 0;
+    let (mut in_authority, mut in_string, mut escaped) = (true, false, false);
+
+    for c in rendered.chars() {
+        match c {
+            VALUE_START => depth += 1,
+            VALUE_END if depth > 1 => depth -= 1,
+            VALUE_END => {
+                depth = 0;
+                let value: String = std::mem::take(&mut value);
+                match context {
+                    Context::Url if !in_authority => out
+                        .extend(percent_encoding::utf8_percent_encode(&value, helpers::URL_ENCODE)),
+                    Context::Json if in_string => {
+                        let json: String = serde_json::Value::String(value).to_string();
+                        out.push_str(&json[1..json.len() - 1])
+                    }
+                    _ => out.push_str(&value),
+                }
+            }
+            c if depth > 0 => value.push(c),
+            c => {
+                match (context, c) {
+                    // The slashes of `://` are part of the scheme, any other slash starts the path
+                    (Context::Url, '/') if in_authority => {
+                        in_authority = out.ends_with(':') || out.ends_with(":/")
+                    }
+                    (Context::Url, '?' | '#') => in_authority = false,
+                    (Context::Json, _) if escaped => escaped = false,
+                    (Context::Json, '\\') if in_string => escaped = true,
+                    (Context::Json, '"') => in_string = !in_string,
+                    _ => {}
+                }
+                out.push(c);
+            }
+        }
+    }
+
+    // A value that is not terminated, which is only the case if it spans several YAML scalars
+    out.push_str(&value);
+    out
+}
+
+/// Apply a function to each string scalar in a request file, with the context of the request
+/// property that it belongs to
+fn map_document(
+    yaml: Mapping,
+    json: bool,
+    f: &mut impl FnMut(String, Context) -> String,
+) -> Mapping {
+    yaml.into_iter()
+        .map(|(key, value)| {
+            let context: Context = match key.as_str() {
+                Some("url") => Context::Url,
+                Some("body") if json => Context::Json,
+                _ => Context::Text,
+            };
+            let key: Value = map_scalars(key, &mut |scalar| f(scalar, Context::Text));
+            (key, map_scalars(value, &mut |scalar| f(scalar, context)))
+        })
+        .collect()
+}
+
+/// Whether the `content-type` header of a request file is a JSON content type
+fn has_json_body(yaml: &Mapping) -> bool {
+    let headers: Option<&Mapping> = yaml.get("headers").and_then(Value::as_mapping);
+    headers.into_iter().flatten().any(|(key, value)| {
+        key.as_str().is_some_and(|key| key.eq_ignore_ascii_case("content-type"))
+            && value.as_str().is_some_and(|value| value.contains("json"))
+    })
+}
+
+/// Apply a function to each string scalar in a YAML document, including keys of mappings, in the
+/// order that they appear in the document
+fn map_scalars(yaml: Value, f: &mut impl FnMut(String) -> String) -> Value {
+    match yaml {
+        Value::String(scalar) => Value::String(f(scalar)),
+        Value::Sequence(seq) => {
+            Value::Sequence(seq.into_iter().map(|v| map_scalars(v, f)).collect())
+        }
+        Value::Mapping(map) => Value::Mapping(
+            map.into_iter().map(|(k, v)| (map_scalars(k, f), map_scalars(v, f))).collect(),
+        ),
+        Value::Tagged(mut tagged) => {
+            tagged.value = map_scalars(tagged.value, f);
+            Value::Tagged(tagged)
+        }
+        other => other,
+    }
 }
 
 fn resolve_values(
@@ -58,15 +299,26 @@ fn resolve_values(
         let all = vars.into_iter().chain(added).collect();
         Ok(all)
     } else {
-        let missing: String = diff.into_iter().next().unwrap();
-        Err(SubstitutionError::MissingValue(missing))
+        let mut missing: Vec<String> = diff.into_iter().collect();
+        missing.sort();
+        Err(SubstitutionError::MissingValues(missing))
     }
 }
 
 #[derive(Debug)]
 pub enum SubstitutionError {
-    MissingValue(String),
-    Rendering,
+    MissingValues(Vec<String>),
+    Rendering(String),
+}
+
+/// Keys without a value when rendering a template, along with where values were looked for
+#[derive(Debug)]
+pub struct MissingKeys {
+    pub keys: Vec<String>,
+    /// Environments files that were read
+    pub env_files: Vec<PathBuf>,
+    /// Environments given with `--env`, or from the configuration
+    pub environments: Vec<String>,
 }
 
 fn merge(mut maps: Vec<Property>) -> HashMap<String, String> {
@@ -85,11 +337,11 @@ fn merge(mut maps: Vec<Property>) -> HashMap<String, String> {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet};
 
     use crate::prop::{ParsePropertyError, Property, Source};
 
-    use super::merge;
+    use super::{merge, resolve_values, yaml_substitution, SubstitutionError};
 
     #[test]
     fn test_merge_properties() -> Result<(), ParsePropertyError> {
@@ -105,4 +357,99 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_report_all_missing_values() {
+        let keys: HashSet<String> =
+            ["TOKEN", "HOST", "USER"].iter().map(|k| k.to_string()).collect();
+        let vars: HashMap<String, String> =
+            [(String::from("HOST"), String::from("localhost"))].into();
+
+        match resolve_values(false, false, false, keys, vars) {
+            Err(SubstitutionError::MissingValues(missing)) => {
+                assert_eq!(vec!["TOKEN", "USER"], missing)
+            }
+            _ => panic!("Expected missing values"),
+        }
+    }
+
+    #[test]
+    fn test_yaml_substitution() -> Result<(), ParsePropertyError> {
+        let props: Vec<Property> = vec![
+            Property::new(String::from("TOKEN"), String::from("a: \"b\"\nc"), Source::Arg)?,
+            Property::new(String::from("NAME"), String::from("Jo \"J\""), Source::Arg)?,
+        ];
+
+        let input = "url: /users\nheaders:\n  authorization: Bearer {{TOKEN}}\nbody: |\n  {\"name\": {{json NAME}}}\n";
+        let rendered: String =
+            yaml_substitution(input.into(), props.clone(), false, false, false).unwrap();
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&rendered).unwrap();
+        assert_eq!(yaml["headers"]["authorization"], "Bearer a: \"b\"\nc");
+        assert_eq!(yaml["body"], "{\"name\": \"Jo \\\"J\\\"\"}\n");
+
+        // Templates outside of string scalars are rendered as text
+        let input = "url: /users\n{{#if NAME}}\nquery:\n  name: x\n{{/if}}\n";
+        let rendered: String = yaml_substitution(input.into(), props, false, false, false).unwrap();
+        assert_eq!("url: /users\nquery:\n  name: x\n", rendered);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_escape_values_by_context() -> Result<(), ParsePropertyError> {
+        let props: Vec<Property> = vec![
+            Property::new(String::from("NAME"), String::from("a\"b&c"), Source::Arg)?,
+            Property::new(String::from("TAGS"), String::from("[1, 2]"), Source::Arg)?,
+            Property::new(String::from("ID"), String::from("x/y?z#w"), Source::Arg)?,
+        ];
+
+        let input = r#"
+url: https://{{NAME}}.example.com/users/{{ID}}?x={{NAME}}&y={{urlencode NAME}}#{{ID}}
+headers:
+  content-type: application/json
+  x-name: "{{NAME}}"
+body: '{"name": "{{NAME}}", "tags": {{TAGS}}, "json": {{json NAME}}, "upper": "{{upper NAME}}"}'
+"#;
+        let rendered: String =
+            yaml_substitution(input.into(), props.clone(), false, false, false).unwrap();
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&rendered).unwrap();
+        assert_eq!(
+            yaml["url"],
+            "https://a\"b&c.example.com/users/x%2Fy%3Fz%23w?x=a%22b%26c&y=a%22b%26c#x%2Fy%3Fz%23w"
+        );
+        assert_eq!(yaml["headers"]["x-name"], "a\"b&c");
+
+        let body: serde_json::Value = serde_json::from_str(yaml["body"].as_str().unwrap()).unwrap();
+        let expected = serde_json::json!({
+            "name": "a\"b&c",
+            "tags": [1, 2],
+            "json": "a\"b&c",
+            "upper": "A\"B&C",
+        });
+        assert_eq!(expected, body);
+
+        // Without a JSON content type, the body is text and values are inserted as they are
+        let input = "url: /users\nbody: '{\"name\": \"{{NAME}}\"}'\n";
+        let rendered: String =
+            yaml_substitution(input.into(), props.clone(), false, false, false).unwrap();
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&rendered).unwrap();
+        assert_eq!(yaml["body"], "{\"name\": \"a\"b&c\"}");
+
+        // Values are escaped in the same way when templates are outside of string scalars
+        let input = r#"
+url: /users/{{ID}}
+{{#if NAME}}
+headers:
+  content-type: application/json
+body: '{"name": "{{NAME}}"}'
+{{/if}}
+"#;
+        let rendered: String = yaml_substitution(input.into(), props, false, false, false).unwrap();
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&rendered).unwrap();
+        assert_eq!(yaml["url"], "/users/x%2Fy%3Fz%23w");
+        let body: serde_json::Value = serde_json::from_str(yaml["body"].as_str().unwrap()).unwrap();
+        assert_eq!(serde_json::json!({"name": "a\"b&c"}), body);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,89 @@
+use std::path::{Path, PathBuf};
+
+use syntect::highlighting::{Theme, ThemeSet};
+
+use crate::config;
+use crate::error::FireError;
+
+/// Theme used for syntax highlighting when no other theme is selected
+pub const DEFAULT: &str = "base16-mocha.dark";
+
+/// Themes bundled with syntect, and custom `.tmTheme` files in the `themes` directory in the
+/// configuration directory, which are named after their file name without the extension. A custom
+/// theme with the same name as a bundled theme replaces it.
+pub fn themes() -> ThemeSet {
+    themes_from(custom_dir().as_deref())
+}
+
+/// Load a theme by its name, or the default theme if no name is given
+pub fn load(name: Option<&str>) -> Result<Theme, FireError> {
+    load_from(custom_dir().as_deref(), name)
+}
+
+/// Load a theme by its name from the bundled themes and the custom themes in `dir`, if any
+pub fn load_from(dir: Option<&Path>, name: Option<&str>) -> Result<Theme, FireError> {
+    let name: &str = name.unwrap_or(DEFAULT);
+    let mut themes: ThemeSet = themes_from(dir);
+    match themes.themes.remove(name) {
+        Some(theme) => Ok(theme),
+        None => {
+            let names: Vec<String> = themes.themes.into_keys().collect();
+            Err(FireError::Config(format!(
+                "No theme '{name}', available themes are {}",
+                names.join(", ")
+            )))
+        }
+    }
+}
+
+fn themes_from(dir: Option<&Path>) -> ThemeSet {
+    let mut themes: ThemeSet = ThemeSet::load_defaults();
+    if let Some(dir) = dir.filter(|dir| dir.is_dir()) {
+        if let Err(e) = themes.add_from_folder(dir) {
+            log::warn!("Unable to load themes from {}: {e}", dir.display());
+        }
+    }
+    themes
+}
+
+fn custom_dir() -> Option<PathBuf> {
+    config::dir().map(|dir| dir.join("themes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use httpx::test_util::TempDir;
+
+    use super::load_from;
+
+    const THEME: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>name</key>
+    <string>Custom</string>
+    <key>settings</key>
+    <array>
+        <dict>
+            <key>settings</key>
+            <dict>
+                <key>foreground</key>
+                <string>#FF0000</string>
+            </dict>
+        </dict>
+    </array>
+</dict>
+</plist>"#;
+
+    #[test]
+    fn test_load_custom_theme() {
+        let dir = TempDir::new("fire-themes");
+        std::fs::write(dir.join("custom.tmTheme"), THEME).unwrap();
+        let dir: Option<&Path> = Some(dir.path());
+
+        assert_eq!(Some(String::from("Custom")), load_from(dir, Some("custom")).unwrap().name);
+        assert!(load_from(dir, None).is_ok());
+        assert!(load_from(dir, Some("missing")).is_err());
+    }
+}